@@ -0,0 +1,404 @@
+use std::ffi::{c_int, CStr};
+use std::fs::File;
+use std::io::BufReader;
+use std::mem::MaybeUninit;
+use std::path::Path;
+use std::{ptr, slice};
+
+use image::codecs::gif::GifDecoder;
+use image::codecs::png::PngDecoder;
+use image::codecs::webp::WebPDecoder;
+use image::imageops::{self, FilterType};
+use image::{AnimationDecoder, DynamicImage, Frame, ImageFormat, RgbaImage};
+use libwebp_sys as webp_sys;
+use serde::Deserialize;
+
+use crate::quantize::{self, QuantizeOptions};
+use crate::webp_output::WebpOptions;
+
+// ============================================================================
+// アニメーション画像 (GIF / APNG / WebP)
+// ============================================================================
+
+/// アニメーション処理オプション
+#[derive(Debug, Clone, Deserialize)]
+pub struct AnimationOptions {
+    // 連続する同一フレームを 1 枚にまとめる (表示時間は合算)
+    #[serde(default = "crate::default_true")]
+    pub drop_duplicates: bool,
+    // APNG 出力時に全フレーム共通の量子化パレットを使う
+    #[serde(default)]
+    pub shared_palette: bool,
+}
+
+impl Default for AnimationOptions {
+    fn default() -> Self {
+        AnimationOptions {
+            drop_duplicates: true,
+            shared_palette: false,
+        }
+    }
+}
+
+/// デコード済みフレーム (キャンバス全体に合成済み)
+#[derive(Debug, Clone)]
+pub struct AnimationFrame {
+    pub image: RgbaImage,
+    pub delay_ms: u32,
+}
+
+/// デコード済みアニメーション
+#[derive(Debug, Clone)]
+pub struct Animation {
+    pub width: u32,
+    pub height: u32,
+    pub frames: Vec<AnimationFrame>,
+}
+
+impl Animation {
+    /// 先頭フレーム (プレースホルダー生成などに使用)
+    pub fn first_frame(&self) -> DynamicImage {
+        DynamicImage::ImageRgba8(self.frames[0].image.clone())
+    }
+}
+
+/// 全フレームをデコードする (2 フレーム以上ない場合は None)
+pub fn decode(path: &Path, format: Option<ImageFormat>) -> Result<Option<Animation>, String> {
+    let open = || {
+        File::open(path)
+            .map(BufReader::new)
+            .map_err(|e| e.to_string())
+    };
+    let decode_error = |e: image::ImageError| format!("frame decode: {}", e);
+
+    let frames: Vec<Frame> = match format {
+        Some(ImageFormat::Gif) => GifDecoder::new(open()?)
+            .and_then(|d| d.into_frames().collect_frames())
+            .map_err(decode_error)?,
+        Some(ImageFormat::Png) => {
+            let decoder = PngDecoder::new(open()?).map_err(decode_error)?;
+            if !decoder.is_apng().map_err(decode_error)? {
+                return Ok(None);
+            }
+            decoder
+                .apng()
+                .and_then(|d| d.into_frames().collect_frames())
+                .map_err(decode_error)?
+        }
+        Some(ImageFormat::WebP) => {
+            let decoder = WebPDecoder::new(open()?).map_err(decode_error)?;
+            if !decoder.has_animation() {
+                return Ok(None);
+            }
+            decoder
+                .into_frames()
+                .collect_frames()
+                .map_err(decode_error)?
+        }
+        _ => return Ok(None),
+    };
+
+    if frames.len() < 2 {
+        return Ok(None);
+    }
+
+    let frames: Vec<AnimationFrame> = frames
+        .into_iter()
+        .map(|frame| {
+            let (numer, denom) = frame.delay().numer_denom_ms();
+            AnimationFrame {
+                delay_ms: numer / denom.max(1),
+                image: frame.into_buffer(),
+            }
+        })
+        .collect();
+    let (width, height) = frames[0].image.dimensions();
+
+    Ok(Some(Animation {
+        width,
+        height,
+        frames,
+    }))
+}
+
+/// 連続する同一フレームを除去し、表示時間を直前のフレームに加算する
+/// 戻り値は除去したフレーム数
+pub fn drop_duplicate_frames(animation: &mut Animation) -> usize {
+    let before = animation.frames.len();
+    let mut frames: Vec<AnimationFrame> = Vec::with_capacity(before);
+
+    for frame in animation.frames.drain(..) {
+        match frames.last_mut() {
+            Some(last) if last.image == frame.image => last.delay_ms += frame.delay_ms,
+            _ => frames.push(frame),
+        }
+    }
+
+    animation.frames = frames;
+    before - animation.frames.len()
+}
+
+/// 全フレームをリサイズする
+pub fn resize(animation: &Animation, width: u32, height: u32) -> Animation {
+    Animation {
+        width,
+        height,
+        frames: animation
+            .frames
+            .iter()
+            .map(|frame| AnimationFrame {
+                image: imageops::resize(&frame.image, width, height, FilterType::Lanczos3),
+                delay_ms: frame.delay_ms,
+            })
+            .collect(),
+    }
+}
+
+/// アニメーション WebP としてエンコード
+/// webp クレートの AnimEncoder は終端を タイムスタンプ 0 で追加するため libwebp に拒否され、
+/// 最終フレームの表示時間が他のフレームの平均になってしまう。終端の時刻を正しく渡すため libwebp を直接使う
+pub fn encode_webp(
+    animation: &Animation,
+    quality: u8,
+    options: &WebpOptions,
+) -> Result<Vec<u8>, String> {
+    let config = options.config(quality)?;
+    let encode_error = |e: String| format!("animation: {}", e);
+    let (width, height) = (animation.width as c_int, animation.height as c_int);
+
+    let encoder = AnimEncoder::new(width, height).map_err(encode_error)?;
+
+    // タイムスタンプは各フレームの開始時刻 (ms)
+    let mut timestamp: c_int = 0;
+    for frame in &animation.frames {
+        encoder
+            .add(frame.image.as_raw(), width, height, timestamp, &config)
+            .map_err(encode_error)?;
+        timestamp += frame.delay_ms as c_int;
+    }
+
+    // 終端の時刻を渡して最終フレームの表示時間を確定させる
+    encoder.finish(timestamp).map_err(encode_error)
+}
+
+/// libwebp のアニメーションエンコーダー (破棄時に解放する)
+struct AnimEncoder(*mut webp_sys::WebPAnimEncoder);
+
+impl AnimEncoder {
+    /// ループ無限のエンコーダーを作成する
+    fn new(width: c_int, height: c_int) -> Result<Self, String> {
+        let abi_version = webp_sys::WEBP_MUX_ABI_VERSION as c_int;
+        unsafe {
+            let mut options = MaybeUninit::<webp_sys::WebPAnimEncoderOptions>::uninit();
+            if webp_sys::WebPAnimEncoderOptionsInitInternal(options.as_mut_ptr(), abi_version) == 0
+            {
+                return Err("libwebp ABI version mismatch".to_string());
+            }
+            let mut options = options.assume_init();
+            options.anim_params.loop_count = 0;
+
+            let encoder =
+                webp_sys::WebPAnimEncoderNewInternal(width, height, &options, abi_version);
+            if encoder.is_null() {
+                return Err("WebPAnimEncoderNew failed".to_string());
+            }
+            Ok(AnimEncoder(encoder))
+        }
+    }
+
+    /// RGBA のフレームを追加する
+    fn add(
+        &self,
+        rgba: &[u8],
+        width: c_int,
+        height: c_int,
+        timestamp: c_int,
+        config: &webp::WebPConfig,
+    ) -> Result<(), String> {
+        let mut picture =
+            webp_sys::WebPPicture::new().map_err(|_| "WebPPictureInit failed".to_string())?;
+        picture.use_argb = 1;
+        picture.width = width;
+        picture.height = height;
+        let added = unsafe {
+            let ok = webp_sys::WebPPictureImportRGBA(&mut picture, rgba.as_ptr(), width * 4) != 0
+                && webp_sys::WebPAnimEncoderAdd(self.0, &mut picture, timestamp, config) != 0;
+            webp_sys::WebPPictureFree(&mut picture);
+            ok
+        };
+        if added {
+            Ok(())
+        } else {
+            Err(self.error())
+        }
+    }
+
+    /// 終端の時刻を渡してエンコード結果を取り出す
+    fn finish(self, end_timestamp: c_int) -> Result<Vec<u8>, String> {
+        unsafe {
+            if webp_sys::WebPAnimEncoderAdd(self.0, ptr::null_mut(), end_timestamp, ptr::null())
+                == 0
+            {
+                return Err(self.error());
+            }
+            let mut data = webp_sys::WebPData::default();
+            if webp_sys::WebPAnimEncoderAssemble(self.0, &mut data) == 0 {
+                return Err(self.error());
+            }
+            let bytes = slice::from_raw_parts(data.bytes, data.size).to_vec();
+            webp_sys::WebPDataClear(&mut data);
+            Ok(bytes)
+        }
+    }
+
+    /// 直前の操作のエラーメッセージ
+    fn error(&self) -> String {
+        let message = unsafe { webp_sys::WebPAnimEncoderGetError(self.0) };
+        if message.is_null() {
+            return "unknown error".to_string();
+        }
+        unsafe { CStr::from_ptr(message) }
+            .to_string_lossy()
+            .into_owned()
+    }
+}
+
+impl Drop for AnimEncoder {
+    fn drop(&mut self) {
+        unsafe { webp_sys::WebPAnimEncoderDelete(self.0) }
+    }
+}
+
+/// APNG としてエンコード
+/// palette 指定時は全フレーム共通の量子化パレット (クオリティ, 減色設定) でインデックスカラー化する
+pub fn encode_apng(
+    animation: &Animation,
+    palette: Option<(u8, &QuantizeOptions)>,
+) -> Result<Vec<u8>, String> {
+    let encode_error = |e: png::EncodingError| format!("APNG: {}", e);
+
+    // 共通パレットでのリマップ (フレームごとのインデックス列)
+    let indexed = match palette {
+        Some((quality, options)) => {
+            let images: Vec<&RgbaImage> = animation.frames.iter().map(|f| &f.image).collect();
+            Some(quantize::quantize_shared(&images, quality, options)?)
+        }
+        None => None,
+    };
+
+    let mut data = Vec::new();
+    {
+        let mut encoder = png::Encoder::new(&mut data, animation.width, animation.height);
+        encoder.set_depth(png::BitDepth::Eight);
+        match indexed {
+            Some((ref palette, _)) => {
+                encoder.set_color(png::ColorType::Indexed);
+                encoder.set_palette(
+                    palette
+                        .iter()
+                        .flat_map(|c| [c.r, c.g, c.b])
+                        .collect::<Vec<u8>>(),
+                );
+                encoder.set_trns(palette.iter().map(|c| c.a).collect::<Vec<u8>>());
+            }
+            None => encoder.set_color(png::ColorType::Rgba),
+        }
+        encoder
+            .set_animated(animation.frames.len() as u32, 0)
+            .map_err(encode_error)?;
+
+        let mut writer = encoder.write_header().map_err(encode_error)?;
+        for (i, frame) in animation.frames.iter().enumerate() {
+            let delay = frame.delay_ms.min(u16::MAX as u32) as u16;
+            writer.set_frame_delay(delay, 1000).map_err(encode_error)?;
+            match indexed {
+                Some((_, ref frames)) => writer.write_image_data(&frames[i]),
+                None => writer.write_image_data(frame.image.as_raw()),
+            }
+            .map_err(encode_error)?;
+        }
+        writer.finish().map_err(encode_error)?;
+    }
+
+    Ok(data)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use image::Rgba;
+
+    use super::*;
+
+    fn frame(value: u8, delay_ms: u32) -> AnimationFrame {
+        AnimationFrame {
+            image: RgbaImage::from_pixel(8, 8, Rgba([value, 255 - value, 0, 255])),
+            delay_ms,
+        }
+    }
+
+    fn webp_delays(data: &[u8]) -> Vec<u32> {
+        WebPDecoder::new(Cursor::new(data))
+            .unwrap()
+            .into_frames()
+            .collect_frames()
+            .unwrap()
+            .iter()
+            .map(|f| {
+                let (numer, denom) = f.delay().numer_denom_ms();
+                numer / denom
+            })
+            .collect()
+    }
+
+    #[test]
+    fn webp_keeps_hold_on_final_frame() {
+        // 最終フレームを 3 枚重ねた GIF によくある「最後で止める」アニメーション
+        let mut animation = Animation {
+            width: 8,
+            height: 8,
+            frames: vec![
+                frame(0, 100),
+                frame(80, 100),
+                frame(160, 100),
+                frame(160, 100),
+                frame(160, 100),
+            ],
+        };
+        assert_eq!(drop_duplicate_frames(&mut animation), 2);
+        assert_eq!(animation.frames[2].delay_ms, 300);
+
+        let options = WebpOptions {
+            lossless: true,
+            ..Default::default()
+        };
+        let data = encode_webp(&animation, 80, &options).unwrap();
+        assert_eq!(webp_delays(&data), [100, 100, 300]);
+    }
+
+    #[test]
+    fn shared_palette_keeps_every_frame_color() {
+        let animation = Animation {
+            width: 8,
+            height: 8,
+            frames: vec![frame(0, 100), frame(80, 100), frame(160, 100)],
+        };
+        let data = encode_apng(&animation, Some((100, &QuantizeOptions::default()))).unwrap();
+
+        let frames = PngDecoder::new(Cursor::new(data))
+            .unwrap()
+            .apng()
+            .unwrap()
+            .into_frames()
+            .collect_frames()
+            .unwrap();
+        assert_eq!(frames.len(), 3);
+        for (decoded, original) in frames.iter().zip(&animation.frames) {
+            assert_eq!(
+                decoded.buffer().get_pixel(4, 4),
+                original.image.get_pixel(4, 4)
+            );
+        }
+    }
+}
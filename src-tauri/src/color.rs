@@ -0,0 +1,137 @@
+use image::{DynamicImage, GenericImageView};
+use imagequant::RGBA;
+use serde::{Deserialize, Serialize};
+
+// ============================================================================
+// 色解析 (代表色・パレット・アルファ/グレースケール判定)
+// ============================================================================
+
+/// 代表パレットの色数
+const PALETTE_SIZE: u32 = 6;
+/// パレット抽出用の縮小サイズ (長辺)
+const PALETTE_SAMPLE_SIZE: u32 = 64;
+
+/// パレットの 1 色
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PaletteColor {
+    // "#rrggbb"
+    pub color: String,
+    // 画像中に占める割合 (0.0 〜 1.0)
+    pub ratio: f32,
+}
+
+/// 色解析結果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ColorAnalysis {
+    // 平均色 "#rrggbb" (アルファで重み付け)
+    pub average_color: String,
+    // 支配色 "#rrggbb" (パレットの先頭)
+    pub dominant_color: String,
+    // 代表パレット (割合の大きい順)
+    pub palette: Vec<PaletteColor>,
+    // 半透明・透明ピクセルが実際に存在するか
+    pub has_alpha: bool,
+    // 全ピクセルが無彩色か
+    pub is_grayscale: bool,
+}
+
+/// デコード済み画像の色を解析する
+pub fn analyze(img: &DynamicImage) -> Result<ColorAnalysis, String> {
+    let mut has_alpha = false;
+    let mut is_grayscale = true;
+
+    // 全ピクセルを 1 回走査して平均色・アルファ・グレースケールを判定
+    let mut sum = [0u64; 3];
+    let mut weight = 0u64;
+    for (_, _, p) in img.pixels() {
+        let a = p[3] as u64;
+        sum[0] += p[0] as u64 * a;
+        sum[1] += p[1] as u64 * a;
+        sum[2] += p[2] as u64 * a;
+        weight += a;
+
+        if p[3] < 255 {
+            has_alpha = true;
+        }
+        if p[0] != p[1] || p[1] != p[2] {
+            is_grayscale = false;
+        }
+    }
+
+    let average = sum.map(|channel| channel.checked_div(weight).unwrap_or(0) as u8);
+
+    let palette = extract_palette(img)?;
+    let dominant_color = palette
+        .first()
+        .map(|c| c.color.clone())
+        .unwrap_or_else(|| hex(average[0], average[1], average[2]));
+
+    Ok(ColorAnalysis {
+        average_color: hex(average[0], average[1], average[2]),
+        dominant_color,
+        palette,
+        has_alpha,
+        is_grayscale,
+    })
+}
+
+/// 縮小画像を imagequant で量子化して代表パレットを抽出
+fn extract_palette(img: &DynamicImage) -> Result<Vec<PaletteColor>, String> {
+    let small = img
+        .thumbnail(PALETTE_SAMPLE_SIZE, PALETTE_SAMPLE_SIZE)
+        .to_rgba8();
+    let (width, height) = small.dimensions();
+
+    let pixels: Vec<RGBA> = small
+        .pixels()
+        .map(|p| RGBA::new(p[0], p[1], p[2], p[3]))
+        .collect();
+
+    let mut attrs = imagequant::new();
+    attrs
+        .set_max_colors(PALETTE_SIZE)
+        .map_err(|e| format!("imagequant: {:?}", e))?;
+    attrs
+        .set_speed(10)
+        .map_err(|e| format!("imagequant: {:?}", e))?;
+
+    let mut liq_image = attrs
+        .new_image(pixels.as_slice(), width as usize, height as usize, 0.0)
+        .map_err(|e| format!("imagequant: {:?}", e))?;
+    let mut quantized = attrs
+        .quantize(&mut liq_image)
+        .map_err(|e| format!("imagequant: {:?}", e))?;
+    let _ = quantized.set_dithering_level(0.0);
+    let (palette, indexed_pixels) = quantized
+        .remapped(&mut liq_image)
+        .map_err(|e| format!("imagequant remap: {:?}", e))?;
+
+    // 各パレット色の出現数を数える (完全透明ピクセルは除外)
+    let mut counts = vec![0usize; palette.len()];
+    for &index in &indexed_pixels {
+        if palette[index as usize].a > 0 {
+            counts[index as usize] += 1;
+        }
+    }
+    let total = counts.iter().sum::<usize>().max(1);
+
+    let mut colors: Vec<(usize, &RGBA)> = counts
+        .into_iter()
+        .zip(palette.iter())
+        .filter(|(count, _)| *count > 0)
+        .collect();
+    colors.sort_by_key(|(count, _)| std::cmp::Reverse(*count));
+
+    Ok(colors
+        .into_iter()
+        .map(|(count, c)| PaletteColor {
+            color: hex(c.r, c.g, c.b),
+            ratio: count as f32 / total as f32,
+        })
+        .collect())
+}
+
+/// "#rrggbb" 形式に変換
+fn hex(r: u8, g: u8, b: u8) -> String {
+    format!("#{:02x}{:02x}{:02x}", r, g, b)
+}
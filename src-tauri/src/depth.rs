@@ -0,0 +1,106 @@
+use std::borrow::Cow;
+
+use image::{DynamicImage, RgbaImage};
+
+// ============================================================================
+// 高ビット深度 (16 ビット / 浮動小数点) 画像の 8 ビット化
+// ============================================================================
+
+/// 8x8 ベイヤー行列 (0 〜 63)
+const BAYER_8X8: [[u16; 8]; 8] = [
+    [0, 32, 8, 40, 2, 34, 10, 42],
+    [48, 16, 56, 24, 50, 18, 58, 26],
+    [12, 44, 4, 36, 14, 46, 6, 38],
+    [60, 28, 52, 20, 62, 30, 54, 22],
+    [3, 35, 11, 43, 1, 33, 9, 41],
+    [51, 19, 59, 27, 49, 17, 57, 25],
+    [15, 47, 7, 39, 13, 45, 5, 37],
+    [63, 31, 55, 23, 61, 29, 53, 21],
+];
+
+/// 1 チャンネルあたりのビット深度
+pub fn bit_depth(img: &DynamicImage) -> u8 {
+    let color = img.color();
+    (color.bits_per_pixel() / color.channel_count().max(1) as u16) as u8
+}
+
+/// 8 ビットを超える深度を持つか
+pub fn is_high_depth(img: &DynamicImage) -> bool {
+    bit_depth(img) > 8
+}
+
+/// RGBA 8 ビットに変換する
+/// 高ビット深度の場合は切り捨てではなく順序ディザリングで階調を保つ
+pub fn to_rgba8(img: &DynamicImage) -> RgbaImage {
+    if !is_high_depth(img) {
+        return img.to_rgba8();
+    }
+
+    let rgba16 = img.to_rgba16();
+    let (width, height) = rgba16.dimensions();
+    RgbaImage::from_fn(width, height, |x, y| {
+        // 閾値を 0 〜 256 に広げ、16 ビット値 (0 〜 65535) を 257 で割る際に加える
+        let threshold = BAYER_8X8[(y % 8) as usize][(x % 8) as usize] * 257 / 64;
+        let p = rgba16.get_pixel(x, y);
+        image::Rgba(p.0.map(|v| ((v as u32 + threshold as u32) / 257).min(255) as u8))
+    })
+}
+
+/// 8 ビットにディザリングした RGBA 画像
+pub fn dither_to_8bit(img: &DynamicImage) -> DynamicImage {
+    DynamicImage::ImageRgba8(to_rgba8(img))
+}
+
+/// 16 ビット PNG として書き出せる形式にする (浮動小数点は 16 ビット整数へ)
+pub fn to_png16(img: &DynamicImage) -> Cow<'_, DynamicImage> {
+    match img {
+        DynamicImage::ImageRgb32F(_) => Cow::Owned(DynamicImage::ImageRgb16(img.to_rgb16())),
+        DynamicImage::ImageRgba32F(_) => Cow::Owned(DynamicImage::ImageRgba16(img.to_rgba16())),
+        _ => Cow::Borrowed(img),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use image::{ImageBuffer, Rgba};
+
+    use super::*;
+
+    #[test]
+    fn smooth_gradient_is_dithered_not_banded() {
+        // 8 ピクセル幅ごとに 16 ビット値で 32 ずつ (8 ビットでは約 1/8 段) 明るくなるグラデーション
+        let level = |x: u32| 0x4000 + (x / 8) as u16 * 32;
+        let img = DynamicImage::ImageRgba16(ImageBuffer::from_fn(256, 8, |x, _| {
+            let v = level(x);
+            Rgba([v, v, v, u16::MAX])
+        }));
+        assert_eq!(bit_depth(&img), 16);
+
+        let dithered = to_rgba8(&img);
+        let block_means: Vec<f64> = (0..32)
+            .map(|block| {
+                let sum: u32 = (0..8)
+                    .flat_map(|y| (0..8).map(move |x| (block * 8 + x, y)))
+                    .map(|(x, y)| dithered.get_pixel(x, y)[0] as u32)
+                    .sum();
+                sum as f64 / 64.0
+            })
+            .collect();
+
+        // 各ブロックの平均は元の階調に近く、ブロックごとに必ず明るくなる (切り捨てでは数ブロックずつ同じ値になる)
+        for (block, mean) in block_means.iter().enumerate() {
+            let expected = level(block as u32 * 8) as f64 / 257.0;
+            assert!(
+                (mean - expected).abs() < 1.0 / 16.0,
+                "{} != {}",
+                mean,
+                expected
+            );
+        }
+        assert!(block_means.windows(2).all(|w| w[0] < w[1]));
+
+        // 比較: ディザリングなしの変換では隣り合うブロックが同じ値になる
+        let truncated = img.to_rgba8();
+        assert_eq!(truncated.get_pixel(0, 0), truncated.get_pixel(8, 0));
+    }
+}
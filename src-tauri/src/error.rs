@@ -0,0 +1,303 @@
+use std::fmt;
+
+use serde::Serialize;
+
+use crate::i18n::Locale;
+use crate::OutputFormat;
+
+// ============================================================================
+// エラー型 (フロントエンドは code で分岐する)
+// ============================================================================
+
+/// エラーの種類
+/// code (スネークケースの variant 名) と details は互換性のため変更しない
+#[derive(Debug, Clone, Serialize, PartialEq)]
+#[serde(tag = "code", content = "details", rename_all = "snake_case")]
+pub enum ErrorKind {
+    // ファイルが存在しない
+    NotFound,
+    // 画像を読み込めない・デコードできない
+    DecodeFailed {
+        reason: String,
+    },
+    // 対応していない形式
+    UnsupportedFormat {
+        reason: String,
+    },
+    // リサイズ・追加ステージなどの変換に失敗
+    TransformFailed {
+        reason: String,
+    },
+    // 減色に失敗
+    QuantizeFailed {
+        reason: String,
+    },
+    // 最低クオリティを満たせなかった (quality_fallback が fail の場合)
+    QualityTooLow {
+        min_quality: u8,
+        quality: u8,
+    },
+    // 最低クオリティを満たせずスキップした (quality_fallback が skip の場合)
+    Skipped {
+        min_quality: u8,
+        quality: u8,
+    },
+    // エンコードに失敗
+    EncodeFailed {
+        format: OutputFormat,
+        reason: String,
+    },
+    // oxipng などの最適化に失敗
+    OptimizeFailed {
+        reason: String,
+    },
+    // 出力ファイル・ディレクトリの書き込みに失敗
+    WriteFailed {
+        output_path: String,
+        reason: String,
+    },
+    // オプションの指定が不正 (パレットファイルなど)
+    InvalidOptions {
+        reason: String,
+    },
+}
+
+/// 処理エラー
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct ProcessError {
+    // 対象ファイル (特定のファイルに関係しない場合は None)
+    pub path: Option<String>,
+    #[serde(flatten)]
+    pub kind: ErrorKind,
+}
+
+impl ProcessError {
+    /// ファイルに関するエラー
+    pub fn new(path: &str, kind: ErrorKind) -> Self {
+        ProcessError {
+            path: Some(path.to_string()),
+            kind,
+        }
+    }
+}
+
+impl From<ErrorKind> for ProcessError {
+    fn from(kind: ErrorKind) -> Self {
+        ProcessError { path: None, kind }
+    }
+}
+
+impl ErrorKind {
+    /// 指定した言語のメッセージ
+    pub fn message(&self, locale: Locale) -> String {
+        match (self, locale) {
+            (ErrorKind::NotFound, Locale::Ja) => "ファイルが存在しません".to_string(),
+            (ErrorKind::NotFound, Locale::En) => "File not found".to_string(),
+            (ErrorKind::DecodeFailed { reason }, Locale::Ja) => {
+                format!("画像を開けません - {}", reason)
+            }
+            (ErrorKind::DecodeFailed { reason }, Locale::En) => {
+                format!("Cannot open image - {}", reason)
+            }
+            (ErrorKind::UnsupportedFormat { reason }, Locale::Ja) => {
+                format!("未対応の形式です - {}", reason)
+            }
+            (ErrorKind::UnsupportedFormat { reason }, Locale::En) => {
+                format!("Unsupported format - {}", reason)
+            }
+            (ErrorKind::TransformFailed { reason }, Locale::Ja) => {
+                format!("変換エラー: {}", reason)
+            }
+            (ErrorKind::TransformFailed { reason }, Locale::En) => {
+                format!("Transform error: {}", reason)
+            }
+            (ErrorKind::QuantizeFailed { reason }, Locale::Ja) => {
+                format!("減色エラー: {}", reason)
+            }
+            (ErrorKind::QuantizeFailed { reason }, Locale::En) => {
+                format!("Quantization error: {}", reason)
+            }
+            (
+                ErrorKind::QualityTooLow {
+                    min_quality,
+                    quality,
+                },
+                Locale::Ja,
+            ) => format!("最低クオリティを満たせません ({}-{})", min_quality, quality),
+            (
+                ErrorKind::QualityTooLow {
+                    min_quality,
+                    quality,
+                },
+                Locale::En,
+            ) => format!("Minimum quality not reached ({}-{})", min_quality, quality),
+            (
+                ErrorKind::Skipped {
+                    min_quality,
+                    quality,
+                },
+                Locale::Ja,
+            ) => format!(
+                "最低クオリティを満たせないためスキップ ({}-{})",
+                min_quality, quality
+            ),
+            (
+                ErrorKind::Skipped {
+                    min_quality,
+                    quality,
+                },
+                Locale::En,
+            ) => format!(
+                "Skipped: minimum quality not reached ({}-{})",
+                min_quality, quality
+            ),
+            (ErrorKind::EncodeFailed { format, reason }, Locale::Ja) => {
+                format!("{} エンコードエラー: {}", format.extension(), reason)
+            }
+            (ErrorKind::EncodeFailed { format, reason }, Locale::En) => {
+                format!("{} encode error: {}", format.extension(), reason)
+            }
+            (ErrorKind::OptimizeFailed { reason }, Locale::Ja) => {
+                format!("最適化エラー: {}", reason)
+            }
+            (ErrorKind::OptimizeFailed { reason }, Locale::En) => {
+                format!("Optimization error: {}", reason)
+            }
+            (
+                ErrorKind::WriteFailed {
+                    output_path,
+                    reason,
+                },
+                Locale::Ja,
+            ) => format!("{}: ファイル書き込みエラー: {}", output_path, reason),
+            (
+                ErrorKind::WriteFailed {
+                    output_path,
+                    reason,
+                },
+                Locale::En,
+            ) => format!("{}: File write error: {}", output_path, reason),
+            (ErrorKind::InvalidOptions { reason }, Locale::Ja) => {
+                format!("設定エラー: {}", reason)
+            }
+            (ErrorKind::InvalidOptions { reason }, Locale::En) => {
+                format!("Invalid options: {}", reason)
+            }
+        }
+    }
+}
+
+impl ProcessError {
+    /// 指定した言語のメッセージ (ファイルパス付き)
+    pub fn message(&self, locale: Locale) -> String {
+        match self.path {
+            Some(ref path) => format!("{}: {}", path, self.kind.message(locale)),
+            None => self.kind.message(locale),
+        }
+    }
+}
+
+// ============================================================================
+// 警告 (処理は成功したが注意が必要な事項)
+// ============================================================================
+
+/// 警告の種類
+/// code (スネークケースの variant 名) と details は互換性のため変更しない
+#[derive(Debug, Clone, Serialize, PartialEq)]
+#[serde(tag = "code", content = "details", rename_all = "snake_case")]
+pub enum WarningKind {
+    // 拡張子と内容のフォーマットが一致しない
+    ExtensionMismatch { extension: String, format: String },
+    // アニメーション処理が無効のため先頭フレームのみ出力した
+    FirstFrameOnly { frames: u32 },
+    // 正方形ではないため透明な余白で正方形にした
+    PaddedToSquare { width: u32, height: u32 },
+    // 元画像が最大のアイコンサイズより小さいため拡大した
+    Upscaled { size: u32, width: u32, height: u32 },
+}
+
+/// 警告 (message は結果を返す時点の言語で表示したもの)
+#[derive(Debug, Clone, Serialize)]
+pub struct Warning {
+    #[serde(flatten)]
+    pub kind: WarningKind,
+    pub message: String,
+}
+
+impl WarningKind {
+    /// 指定した言語のメッセージ
+    pub fn message(&self, locale: Locale) -> String {
+        match (self, locale) {
+            (WarningKind::ExtensionMismatch { extension, format }, Locale::Ja) => {
+                format!("拡張子 .{} と内容 ({}) が一致しません", extension, format)
+            }
+            (WarningKind::ExtensionMismatch { extension, format }, Locale::En) => {
+                format!(
+                    "Extension .{} does not match content ({})",
+                    extension, format
+                )
+            }
+            (WarningKind::FirstFrameOnly { frames }, Locale::Ja) => format!(
+                "アニメーション処理が無効のため先頭フレームのみ出力します ({} フレーム中)",
+                frames
+            ),
+            (WarningKind::FirstFrameOnly { frames }, Locale::En) => format!(
+                "Animation handling is off; only the first frame is kept (of {})",
+                frames
+            ),
+            (WarningKind::PaddedToSquare { width, height }, Locale::Ja) => format!(
+                "正方形ではないため透明な余白で正方形にしました ({}x{})",
+                width, height
+            ),
+            (WarningKind::PaddedToSquare { width, height }, Locale::En) => format!(
+                "Not square; padded to a square with transparency ({}x{})",
+                width, height
+            ),
+            (
+                WarningKind::Upscaled {
+                    size,
+                    width,
+                    height,
+                },
+                Locale::Ja,
+            ) => format!(
+                "元画像が {}px より小さいため拡大しています ({}x{})",
+                size, width, height
+            ),
+            (
+                WarningKind::Upscaled {
+                    size,
+                    width,
+                    height,
+                },
+                Locale::En,
+            ) => format!(
+                "Source is smaller than {}px and was upscaled ({}x{})",
+                size, width, height
+            ),
+        }
+    }
+
+    /// 指定した言語のメッセージを付けた警告
+    pub fn render(self, locale: Locale) -> Warning {
+        Warning {
+            message: self.message(locale),
+            kind: self,
+        }
+    }
+}
+
+/// Display は既定の言語 (日本語) で表示する
+impl fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message(Locale::default()))
+    }
+}
+
+impl fmt::Display for ProcessError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message(Locale::default()))
+    }
+}
+
+impl std::error::Error for ProcessError {}
@@ -0,0 +1,94 @@
+use std::borrow::Cow;
+
+use image::{DynamicImage, RgbaImage};
+use imagequant::RGBA;
+
+use crate::animation::Animation;
+use crate::depth;
+use crate::quantize::{self, QuantizeOptions};
+
+// ============================================================================
+// GIF 出力 (imagequant パレット + 1 ビット透過)
+// ============================================================================
+
+/// 静止画を GIF としてエンコード
+pub fn encode(
+    img: &DynamicImage,
+    quality: u8,
+    options: &QuantizeOptions,
+) -> Result<Vec<u8>, String> {
+    let image = quantize::threshold_alpha(&depth::to_rgba8(img));
+    let (palette, indexed) =
+        quantize::quantize(&image, quality, options).map_err(|e| e.to_string())?;
+
+    write(image.width(), image.height(), &palette, vec![(indexed, 0)])
+}
+
+/// アニメーションを全フレーム共通パレットの GIF としてエンコード
+pub fn encode_animation(
+    animation: &Animation,
+    quality: u8,
+    options: &QuantizeOptions,
+) -> Result<Vec<u8>, String> {
+    let images: Vec<RgbaImage> = animation
+        .frames
+        .iter()
+        .map(|f| quantize::threshold_alpha(&f.image))
+        .collect();
+    let image_refs: Vec<&RgbaImage> = images.iter().collect();
+    let (palette, indexed) = quantize::quantize_shared(&image_refs, quality, options)?;
+
+    let frames = indexed
+        .into_iter()
+        .zip(&animation.frames)
+        .map(|(pixels, frame)| (pixels, frame.delay_ms))
+        .collect();
+
+    write(animation.width, animation.height, &palette, frames)
+}
+
+/// インデックス化済みフレーム列 (ピクセル, 表示時間 ms) を GIF として書き出す
+fn write(
+    width: u32,
+    height: u32,
+    palette: &[RGBA],
+    frames: Vec<(Vec<u8>, u32)>,
+) -> Result<Vec<u8>, String> {
+    let too_large = |_| "dimensions exceed the GIF limit of 65535px".to_string();
+    let width = u16::try_from(width).map_err(too_large)?;
+    let height = u16::try_from(height).map_err(too_large)?;
+    let encode_error = |e: gif::EncodingError| e.to_string();
+
+    let global_palette: Vec<u8> = palette.iter().flat_map(|c| [c.r, c.g, c.b]).collect();
+    // 透明色は 1 色のみ (しきい値処理済みなので完全透明のエントリ)
+    let transparent = palette.iter().position(|c| c.a < 128).map(|i| i as u8);
+    let animated = frames.len() > 1;
+
+    let mut data = Vec::new();
+    {
+        let mut encoder =
+            gif::Encoder::new(&mut data, width, height, &global_palette).map_err(encode_error)?;
+        if animated {
+            encoder
+                .set_repeat(gif::Repeat::Infinite)
+                .map_err(encode_error)?;
+        }
+
+        for (pixels, delay_ms) in frames {
+            let frame = gif::Frame {
+                width,
+                height,
+                buffer: Cow::Owned(pixels),
+                transparent,
+                // GIF の表示時間は 1/100 秒単位
+                delay: (delay_ms / 10).min(u16::MAX as u32) as u16,
+                // フレームはキャンバス全体に合成済みなので毎回背景に戻す
+                dispose: gif::DisposalMethod::Background,
+                ..gif::Frame::default()
+            };
+            encoder.write_frame(&frame).map_err(encode_error)?;
+        }
+    }
+
+    Ok(data)
+}
@@ -0,0 +1,50 @@
+use serde::{Deserialize, Serialize};
+
+// ============================================================================
+// 表示言語
+// ============================================================================
+
+/// メッセージの表示言語
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Locale {
+    // 日本語
+    #[default]
+    Ja,
+    // 英語
+    En,
+}
+
+impl Locale {
+    /// 言語に応じて固定の文言を選ぶ
+    pub fn pick(self, ja: &'static str, en: &'static str) -> &'static str {
+        match self {
+            Locale::Ja => ja,
+            Locale::En => en,
+        }
+    }
+}
+
+/// 処理結果の要約 ("{ステップ} | 元サイズ → 結果サイズ bytes (削減率)")
+pub fn summary(locale: Locale, steps: &str, original_size: u64, result_size: u64) -> String {
+    let reduction = if original_size > 0 {
+        (1.0 - result_size as f64 / original_size as f64) * 100.0
+    } else {
+        0.0
+    };
+    let sizes = match locale {
+        Locale::Ja => format!(
+            "{} → {} bytes ({:.1}% 削減)",
+            original_size, result_size, reduction
+        ),
+        Locale::En => format!(
+            "{} → {} bytes ({:.1}% smaller)",
+            original_size, result_size, reduction
+        ),
+    };
+    if steps.is_empty() {
+        sizes
+    } else {
+        format!("{} | {}", steps, sizes)
+    }
+}
@@ -0,0 +1,288 @@
+use std::fs;
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
+
+use image::codecs::ico::{IcoEncoder, IcoFrame};
+use image::imageops::{self, FilterType};
+use image::{DynamicImage, ExtendedColorType, GenericImageView, RgbaImage};
+use serde::{Deserialize, Serialize};
+
+use crate::error::{ErrorKind, ProcessError, Warning, WarningKind};
+use crate::i18n::Locale;
+use crate::pipeline::{encode_png, optimize_png, StepLog};
+use crate::{
+    calculate_new_dimensions, depth, open_image, svg, OutputFormat, OxipngOptions, QualityFallback,
+    QuantizeOptions,
+};
+
+// ============================================================================
+// ファビコン・アプリアイコン一式の生成
+// ============================================================================
+
+/// favicon.ico に含める解像度
+const FAVICON_SIZES: [u32; 3] = [16, 32, 48];
+
+/// apple-touch-icon の解像度
+const APPLE_TOUCH_SIZE: u32 = 180;
+
+/// PWA マニフェスト用アイコンの解像度
+const PWA_SIZES: [u32; 2] = [192, 512];
+
+/// アイコンセット生成オプション
+#[derive(Debug, Clone, Deserialize)]
+pub struct IconOptions {
+    // 出力先ディレクトリ (None の場合は元ファイルと同じ場所)
+    #[serde(default)]
+    pub output_dir: Option<String>,
+    // pngquant のクオリティ
+    #[serde(default = "default_quality")]
+    pub quality: u8,
+    // pngquant による減色
+    #[serde(default = "crate::default_true")]
+    pub quantize_enabled: bool,
+    // oxipng による最適化
+    #[serde(default = "crate::default_true")]
+    pub optimize_enabled: bool,
+    // メッセージ・警告の表示言語
+    #[serde(default)]
+    pub locale: Locale,
+}
+
+fn default_quality() -> u8 {
+    90
+}
+
+/// 生成したファイル 1 件分の情報
+#[derive(Debug, Clone, Serialize)]
+pub struct IconFile {
+    pub output_path: String,
+    // 含まれる解像度 (ICO は複数)
+    pub sizes: Vec<u32>,
+    pub size: u64,
+}
+
+/// アイコンセット生成結果
+#[derive(Debug, Clone, Serialize)]
+pub struct IconSetResult {
+    pub original_size: u64,
+    pub result_size: u64,
+    pub files: Vec<IconFile>,
+    pub manifest_path: String,
+    // <head> に貼り付けるリンクタグ
+    pub html: String,
+    pub message: String,
+    pub warnings: Vec<Warning>,
+}
+
+/// site.webmanifest の icons エントリ
+#[derive(Debug, Clone, Serialize)]
+struct ManifestIcon {
+    src: String,
+    sizes: String,
+    #[serde(rename = "type")]
+    mime_type: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct WebManifest {
+    icons: Vec<ManifestIcon>,
+}
+
+/// 元画像 (SVG は解像度ごとに直接ラスタライズする)
+enum Source {
+    Raster(DynamicImage),
+    Svg(Box<resvg::usvg::Tree>),
+}
+
+impl Source {
+    fn dimensions(&self) -> (u32, u32) {
+        match self {
+            Source::Raster(img) => img.dimensions(),
+            Source::Svg(tree) => svg::intrinsic_size(tree),
+        }
+    }
+
+    /// size x size の正方形に描画する (正方形でない場合は中央に配置して透明で余白を埋める)
+    fn render(&self, size: u32) -> Result<RgbaImage, ErrorKind> {
+        let (orig_w, orig_h) = self.dimensions();
+        let (width, height) =
+            calculate_new_dimensions(orig_w, orig_h, Some(size), Some(size), true);
+        let (width, height) = (width.max(1), height.max(1));
+
+        let img = match self {
+            Source::Raster(img) => img.resize_exact(width, height, FilterType::Lanczos3),
+            Source::Svg(tree) => svg::render(tree, width, height)
+                .map_err(|reason| ErrorKind::TransformFailed { reason })?,
+        };
+        if width == size && height == size {
+            return Ok(depth::to_rgba8(&img));
+        }
+
+        let mut canvas = RgbaImage::new(size, size);
+        imageops::overlay(
+            &mut canvas,
+            &depth::to_rgba8(&img),
+            ((size - width) / 2) as i64,
+            ((size - height) / 2) as i64,
+        );
+        Ok(canvas)
+    }
+}
+
+/// 1 枚の画像から favicon.ico・apple-touch-icon・PWA アイコンと site.webmanifest を生成する
+pub fn generate(path: &Path, options: &IconOptions) -> Result<IconSetResult, ProcessError> {
+    generate_icons(path, options).map_err(|kind| ProcessError::new(&path.to_string_lossy(), kind))
+}
+
+fn generate_icons(path: &Path, options: &IconOptions) -> Result<IconSetResult, ErrorKind> {
+    if !path.exists() {
+        return Err(ErrorKind::NotFound);
+    }
+    let original_size = fs::metadata(path).map(|m| m.len()).unwrap_or(0);
+
+    let source = if svg::is_svg(path) {
+        Source::Svg(Box::new(
+            svg::load(path).map_err(|reason| ErrorKind::DecodeFailed { reason })?,
+        ))
+    } else {
+        Source::Raster(open_image(path)?)
+    };
+
+    let mut warnings = Vec::new();
+    let (orig_w, orig_h) = source.dimensions();
+    if orig_w != orig_h {
+        warnings.push(WarningKind::PaddedToSquare {
+            width: orig_w,
+            height: orig_h,
+        });
+    }
+    let largest = PWA_SIZES[PWA_SIZES.len() - 1];
+    if !matches!(source, Source::Svg(_)) && orig_w.max(orig_h) < largest {
+        warnings.push(WarningKind::Upscaled {
+            size: largest,
+            width: orig_w,
+            height: orig_h,
+        });
+    }
+
+    // 出力先ディレクトリを決定
+    let output_dir = match options.output_dir {
+        Some(ref dir) => PathBuf::from(dir),
+        None => path.parent().unwrap_or(Path::new(".")).to_path_buf(),
+    };
+    if !output_dir.exists() {
+        fs::create_dir_all(&output_dir).map_err(|e| write_failed(&output_dir, e))?;
+    }
+
+    let quantize_options = QuantizeOptions::default();
+    let oxipng_options = OxipngOptions::default();
+    // 小さいサイズは最低クオリティを満たせないことがあるため、その場合はフルカラーで出力する
+    let encode = |size: u32| -> Result<Vec<u8>, ErrorKind> {
+        let img = DynamicImage::ImageRgba8(source.render(size)?);
+        let mut process_steps = StepLog::default();
+        let data = encode_png(
+            &img,
+            options.quality,
+            options
+                .quantize_enabled
+                .then_some((&quantize_options, QualityFallback::Truecolor)),
+            &mut process_steps,
+        )?;
+        optimize_png(
+            data,
+            options.optimize_enabled.then_some(&oxipng_options),
+            &mut process_steps,
+        )
+    };
+
+    let mut files = Vec::new();
+    let mut write = |name: &str, data: &[u8], sizes: Vec<u32>| -> Result<(), ErrorKind> {
+        let output_path = output_dir.join(name);
+        fs::write(&output_path, data).map_err(|e| write_failed(&output_path, e))?;
+        files.push(IconFile {
+            output_path: output_path.to_string_lossy().to_string(),
+            sizes,
+            size: data.len() as u64,
+        });
+        Ok(())
+    };
+
+    // favicon.ico (PNG 埋め込みの複数解像度)
+    let mut frames = Vec::with_capacity(FAVICON_SIZES.len());
+    for size in FAVICON_SIZES {
+        frames.push(
+            IcoFrame::with_encoded(encode(size)?, size, size, ExtendedColorType::Rgba8)
+                .map_err(|e| ico_failed(format!("ICO frame: {}", e)))?,
+        );
+    }
+    let mut ico = Cursor::new(Vec::new());
+    IcoEncoder::new(&mut ico)
+        .encode_images(&frames)
+        .map_err(|e| ico_failed(format!("ICO: {}", e)))?;
+    write("favicon.ico", ico.get_ref(), FAVICON_SIZES.to_vec())?;
+
+    // apple-touch-icon
+    write(
+        "apple-touch-icon.png",
+        &encode(APPLE_TOUCH_SIZE)?,
+        vec![APPLE_TOUCH_SIZE],
+    )?;
+
+    // PWA アイコン
+    let mut icons = Vec::with_capacity(PWA_SIZES.len());
+    for size in PWA_SIZES {
+        let name = format!("icon-{}.png", size);
+        write(&name, &encode(size)?, vec![size])?;
+        icons.push(ManifestIcon {
+            src: format!("/{}", name),
+            sizes: format!("{}x{}", size, size),
+            mime_type: "image/png".to_string(),
+        });
+    }
+
+    // site.webmanifest (icons のみ、既存のマニフェストへ貼り付けて使う)
+    let manifest_path = output_dir.join("site.webmanifest");
+    let json = serde_json::to_string_pretty(&WebManifest { icons })
+        .map_err(|e| write_failed(&manifest_path, e))?;
+    fs::write(&manifest_path, json).map_err(|e| write_failed(&manifest_path, e))?;
+
+    let html = [
+        "<link rel=\"icon\" href=\"/favicon.ico\" sizes=\"any\">",
+        "<link rel=\"apple-touch-icon\" href=\"/apple-touch-icon.png\">",
+        "<link rel=\"manifest\" href=\"/site.webmanifest\">",
+    ]
+    .join("\n");
+
+    let result_size: u64 = files.iter().map(|f| f.size).sum();
+    Ok(IconSetResult {
+        original_size,
+        result_size,
+        message: match options.locale {
+            Locale::Ja => format!("アイコン {} 件を生成: {} bytes", files.len(), result_size),
+            Locale::En => format!("Generated {} icons: {} bytes", files.len(), result_size),
+        },
+        files,
+        manifest_path: manifest_path.to_string_lossy().to_string(),
+        html,
+        warnings: warnings
+            .into_iter()
+            .map(|kind| kind.render(options.locale))
+            .collect(),
+    })
+}
+
+/// 書き込みエラーに変換する
+fn write_failed(output_path: &Path, e: impl ToString) -> ErrorKind {
+    ErrorKind::WriteFailed {
+        output_path: output_path.to_string_lossy().to_string(),
+        reason: e.to_string(),
+    }
+}
+
+/// favicon.ico のエンコードエラー (各フレームは PNG で埋め込む)
+fn ico_failed(reason: String) -> ErrorKind {
+    ErrorKind::EncodeFailed {
+        format: OutputFormat::Png,
+        reason,
+    }
+}
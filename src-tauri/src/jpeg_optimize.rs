@@ -0,0 +1,232 @@
+use std::any::Any;
+use std::mem;
+use std::os::raw::{c_int, c_uint, c_ulong};
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr;
+
+use mozjpeg_sys::*;
+use serde::{Deserialize, Serialize};
+
+// ============================================================================
+// JPEG ロスレス最適化 (DCT 係数はそのままハフマン符号のみ再構築)
+// ============================================================================
+
+/// JPEG 最適化オプション
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JpegOptimizeOptions {
+    // プログレッシブ JPEG に変換する
+    #[serde(default)]
+    pub progressive: bool,
+    // ICC プロファイル (APP2) を残す
+    #[serde(default = "crate::default_true")]
+    pub keep_icc: bool,
+    // EXIF / XMP (APP1) を残す (回転情報もここに含まれるため既定で残す)
+    #[serde(default = "crate::default_true")]
+    pub keep_exif: bool,
+}
+
+impl Default for JpegOptimizeOptions {
+    fn default() -> Self {
+        JpegOptimizeOptions {
+            progressive: false,
+            keep_icc: true,
+            keep_exif: true,
+        }
+    }
+}
+
+/// libjpeg のエラーは panic に変換して呼び出し元で捕捉する
+unsafe extern "C-unwind" fn error_exit(cinfo: &mut jpeg_common_struct) {
+    let format_message = (*cinfo.err).format_message;
+    let buffer = [0u8; 80];
+    if let Some(format_message) = format_message {
+        format_message(cinfo, &buffer);
+    }
+    let len = buffer.iter().position(|&b| b == 0).unwrap_or(buffer.len());
+    panic::resume_unwind(Box::new(
+        String::from_utf8_lossy(&buffer[..len]).into_owned(),
+    ));
+}
+
+/// エラーマネージャー (libjpeg の構造体より先に破棄されないよう Box で保持)
+fn error_manager() -> Box<jpeg_error_mgr> {
+    unsafe {
+        let mut err: Box<jpeg_error_mgr> = Box::new(mem::zeroed());
+        jpeg_std_error(&mut err);
+        err.error_exit = Some(error_exit);
+        err
+    }
+}
+
+/// 伸張側の構造体 (途中でエラーになっても確実に解放する)
+struct Decompress {
+    cinfo: Box<jpeg_decompress_struct>,
+    _err: Box<jpeg_error_mgr>,
+}
+
+impl Decompress {
+    fn new() -> Self {
+        let mut err = error_manager();
+        unsafe {
+            let mut cinfo: Box<jpeg_decompress_struct> = Box::new(mem::zeroed());
+            cinfo.common.err = &mut *err;
+            jpeg_create_decompress(&mut *cinfo);
+            Decompress { cinfo, _err: err }
+        }
+    }
+}
+
+impl Drop for Decompress {
+    fn drop(&mut self) {
+        unsafe { jpeg_destroy_decompress(&mut self.cinfo) }
+    }
+}
+
+/// 圧縮側の構造体 (出力バッファは libjpeg が malloc で確保する)
+struct Compress {
+    cinfo: Box<jpeg_compress_struct>,
+    _err: Box<jpeg_error_mgr>,
+    buffer: *mut u8,
+    size: c_ulong,
+}
+
+impl Compress {
+    fn new() -> Self {
+        let mut err = error_manager();
+        unsafe {
+            let mut cinfo: Box<jpeg_compress_struct> = Box::new(mem::zeroed());
+            cinfo.common.err = &mut *err;
+            jpeg_create_compress(&mut *cinfo);
+            Compress {
+                cinfo,
+                _err: err,
+                buffer: ptr::null_mut(),
+                size: 0,
+            }
+        }
+    }
+}
+
+impl Drop for Compress {
+    fn drop(&mut self) {
+        unsafe {
+            jpeg_destroy_compress(&mut self.cinfo);
+            if !self.buffer.is_null() {
+                libc::free(self.buffer.cast());
+            }
+        }
+    }
+}
+
+/// JPEG をデコードせずに最適化する
+/// メタデータを除去し、ハフマンテーブルを最適化して書き直す (画質は変わらない)
+pub fn optimize(data: &[u8], options: &JpegOptimizeOptions) -> Result<Vec<u8>, String> {
+    panic::catch_unwind(AssertUnwindSafe(|| unsafe { transcode(data, options) }))
+        .map_err(panic_message)
+}
+
+unsafe fn transcode(data: &[u8], options: &JpegOptimizeOptions) -> Vec<u8> {
+    let mut src = Decompress::new();
+    jpeg_mem_src(&mut src.cinfo, data.as_ptr(), data.len() as c_ulong);
+
+    // 残すマーカーのみ保存する (それ以外は読み捨てる)
+    if options.keep_exif {
+        jpeg_save_markers(&mut src.cinfo, jpeg_marker::APP0 as c_int + 1, 0xFFFF);
+    }
+    if options.keep_icc {
+        jpeg_save_markers(&mut src.cinfo, jpeg_marker::APP0 as c_int + 2, 0xFFFF);
+    }
+
+    jpeg_read_header(&mut src.cinfo, 1);
+    let coefficients = jpeg_read_coefficients(&mut src.cinfo);
+
+    let mut dst = Compress::new();
+    jpeg_copy_critical_parameters(&src.cinfo, &mut dst.cinfo);
+    dst.cinfo.optimize_coding = 1;
+    if options.progressive {
+        jpeg_simple_progression(&mut dst.cinfo);
+    } else {
+        // mozjpeg の既定はプログレッシブのため、ベースラインでは走査設定と走査の最適化を外す
+        // (optimize_scans が有効なままだと jpeg_write_coefficients がプログレッシブで書き出す)
+        jpeg_c_set_bool_param(&mut dst.cinfo, J_BOOLEAN_PARAM::JBOOLEAN_OPTIMIZE_SCANS, 0);
+        dst.cinfo.num_scans = 0;
+        dst.cinfo.scan_info = ptr::null();
+    }
+
+    jpeg_mem_dest(&mut dst.cinfo, &mut dst.buffer, &mut dst.size);
+    jpeg_write_coefficients(&mut dst.cinfo, coefficients);
+
+    let mut marker = src.cinfo.marker_list;
+    while !marker.is_null() {
+        let m = &*marker;
+        jpeg_write_marker(
+            &mut dst.cinfo,
+            m.marker as c_int,
+            m.data,
+            m.data_length as c_uint,
+        );
+        marker = m.next;
+    }
+
+    jpeg_finish_compress(&mut dst.cinfo);
+    jpeg_finish_decompress(&mut src.cinfo);
+
+    std::slice::from_raw_parts(dst.buffer, dst.size as usize).to_vec()
+}
+
+/// panic のペイロードからメッセージを取り出す
+fn panic_message(payload: Box<dyn Any + Send>) -> String {
+    match payload.downcast::<String>() {
+        Ok(message) => *message,
+        Err(payload) => payload
+            .downcast_ref::<&str>()
+            .map(|s| s.to_string())
+            .unwrap_or_else(|| "unknown libjpeg error".to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use image::codecs::jpeg::JpegEncoder;
+    use image::RgbImage;
+
+    use super::*;
+
+    /// APP1 (EXIF) を含むベースライン JPEG
+    fn jpeg_with_exif() -> Vec<u8> {
+        let img = RgbImage::from_fn(32, 32, |x, y| image::Rgb([x as u8 * 8, y as u8 * 8, 128]));
+        let mut encoded = Vec::new();
+        JpegEncoder::new(&mut encoded).encode_image(&img).unwrap();
+
+        let exif = b"Exif\0\0MM\0\x2a\0\0\0\x08\0\0";
+        let mut data = encoded[..2].to_vec();
+        data.extend_from_slice(&[0xFF, 0xE1]);
+        data.extend_from_slice(&(exif.len() as u16 + 2).to_be_bytes());
+        data.extend_from_slice(exif);
+        data.extend_from_slice(&encoded[2..]);
+        data
+    }
+
+    fn has_marker(data: &[u8], marker: u8) -> bool {
+        data.windows(2).any(|w| w == [0xFF, marker])
+    }
+
+    #[test]
+    fn baseline_stays_baseline_and_keeps_exif_by_default() {
+        let options: JpegOptimizeOptions = serde_json::from_str("{}").unwrap();
+        let optimized = optimize(&jpeg_with_exif(), &options).unwrap();
+
+        // SOF0 (ベースライン) のみで SOF2 (プログレッシブ) を含まない
+        assert!(has_marker(&optimized, 0xC0));
+        assert!(!has_marker(&optimized, 0xC2));
+        assert!(optimized.windows(4).any(|w| w == b"Exif"));
+    }
+
+    #[test]
+    fn progressive_writes_progressive_frame() {
+        let options: JpegOptimizeOptions =
+            serde_json::from_str(r#"{"progressive": true}"#).unwrap();
+        let optimized = optimize(&jpeg_with_exif(), &options).unwrap();
+        assert!(has_marker(&optimized, 0xC2));
+    }
+}
@@ -0,0 +1,22 @@
+use image::codecs::jpeg::JpegEncoder;
+use image::{DynamicImage, Rgb, RgbImage};
+
+// ============================================================================
+// JPEG 出力 (最適化は jpeg_optimize で行う)
+// ============================================================================
+
+/// 静止画を JPEG としてエンコード (透過を持たないため白背景に合成する)
+pub fn encode(img: &DynamicImage, quality: u8) -> Result<Vec<u8>, String> {
+    let rgba = img.to_rgba8();
+    let rgb = RgbImage::from_fn(rgba.width(), rgba.height(), |x, y| {
+        let [r, g, b, a] = rgba.get_pixel(x, y).0;
+        let blend = |c: u8| ((c as u32 * a as u32 + 255 * (255 - a as u32) + 127) / 255) as u8;
+        Rgb([blend(r), blend(g), blend(b)])
+    });
+
+    let mut data = Vec::new();
+    JpegEncoder::new_with_quality(&mut data, quality.clamp(1, 100))
+        .encode_image(&rgb)
+        .map_err(|e| e.to_string())?;
+    Ok(data)
+}
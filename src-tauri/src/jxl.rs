@@ -0,0 +1,142 @@
+use image::{DynamicImage, GenericImageView};
+use jpegxl_rs::encode::{EncoderFrame, EncoderSpeed};
+use serde::Deserialize;
+
+use crate::depth;
+
+// ============================================================================
+// JPEG XL 出力 (libjxl)
+// ============================================================================
+
+/// JPEG XL エンコードオプション
+#[derive(Debug, Clone, Deserialize)]
+pub struct JxlOptions {
+    // Butteraugli 距離 (0.0 〜 15.0、1.0 で視覚的ロスレス)
+    // None の場合はクオリティから換算する
+    #[serde(default)]
+    pub distance: Option<f32>,
+    // エンコード努力値 (1 〜 10、大きいほど遅く高圧縮)
+    #[serde(default = "default_effort")]
+    pub effort: u8,
+    // ピクセル単位のロスレス圧縮 (クオリティ 100 でも有効)
+    #[serde(default)]
+    pub lossless: bool,
+    // JPEG 入力をデコードせず、元の JPEG に復元可能な形でロスレス変換する
+    #[serde(default = "crate::default_true")]
+    pub lossless_jpeg: bool,
+}
+
+fn default_effort() -> u8 {
+    7
+}
+
+impl Default for JxlOptions {
+    fn default() -> Self {
+        JxlOptions {
+            distance: None,
+            effort: default_effort(),
+            lossless: false,
+            lossless_jpeg: true,
+        }
+    }
+}
+
+impl JxlOptions {
+    /// ロスレス圧縮を行うか (lossless 指定またはクオリティ 100)
+    pub fn is_lossless(&self, quality: u8) -> bool {
+        self.lossless || quality >= 100
+    }
+
+    /// 使用する Butteraugli 距離
+    pub fn distance(&self, quality: u8) -> f32 {
+        self.distance
+            .unwrap_or_else(|| quality_to_distance(quality))
+            .clamp(0.0, 15.0)
+    }
+}
+
+/// クオリティ (0 〜 100) を Butteraugli 距離に換算する (cjxl と同じ式)
+fn quality_to_distance(quality: u8) -> f32 {
+    let quality = quality as f32;
+    if quality >= 30.0 {
+        0.1 + (100.0 - quality) * 0.09
+    } else {
+        6.4 + 2.5_f32.powf((30.0 - quality) / 5.0) / 6.25
+    }
+}
+
+/// 努力値 (1 〜 10) を libjxl の速度設定に変換
+fn speed(effort: u8) -> EncoderSpeed {
+    match effort {
+        0 | 1 => EncoderSpeed::Lightning,
+        2 => EncoderSpeed::Thunder,
+        3 => EncoderSpeed::Falcon,
+        4 => EncoderSpeed::Cheetah,
+        5 => EncoderSpeed::Hare,
+        6 => EncoderSpeed::Wombat,
+        7 => EncoderSpeed::Squirrel,
+        8 => EncoderSpeed::Kitten,
+        9 => EncoderSpeed::Tortoise,
+        _ => EncoderSpeed::Glacier,
+    }
+}
+
+/// デコード済み画像を JPEG XL としてエンコードする (8 ビットを超える深度は 16 ビットのまま渡す)
+pub fn encode(img: &DynamicImage, quality: u8, options: &JxlOptions) -> Result<Vec<u8>, String> {
+    let (width, height) = img.dimensions();
+    let has_alpha = img.color().has_alpha();
+    let channels = if has_alpha { 4 } else { 3 };
+    let lossless = options.is_lossless(quality);
+
+    let mut encoder = jpegxl_rs::encoder_builder()
+        .has_alpha(has_alpha)
+        .lossless(lossless)
+        .uses_original_profile(lossless)
+        .quality(if lossless {
+            0.0
+        } else {
+            options.distance(quality)
+        })
+        .speed(speed(options.effort))
+        .build()
+        .map_err(|e| format!("encoder init: {}", e))?;
+
+    let result = if depth::is_high_depth(img) {
+        let samples = if has_alpha {
+            img.to_rgba16().into_raw()
+        } else {
+            img.to_rgb16().into_raw()
+        };
+        encoder.encode_frame::<u16, u8>(
+            &EncoderFrame::new(&samples).num_channels(channels),
+            width,
+            height,
+        )
+    } else {
+        let samples = if has_alpha {
+            img.to_rgba8().into_raw()
+        } else {
+            img.to_rgb8().into_raw()
+        };
+        encoder.encode_frame::<u8, u8>(
+            &EncoderFrame::new(&samples).num_channels(channels),
+            width,
+            height,
+        )
+    };
+    result.map(|result| result.data).map_err(|e| e.to_string())
+}
+
+/// JPEG のバイト列を DCT 係数のまま JPEG XL に変換する (元の JPEG にビット単位で復元可能)
+pub fn transcode_jpeg(data: &[u8], options: &JxlOptions) -> Result<Vec<u8>, String> {
+    let mut encoder = jpegxl_rs::encoder_builder()
+        .use_container(true)
+        .speed(speed(options.effort))
+        .build()
+        .map_err(|e| format!("encoder init: {}", e))?;
+
+    encoder
+        .encode_jpeg(data)
+        .map(|result| result.data)
+        .map_err(|e| format!("JPEG transcode: {}", e))
+}
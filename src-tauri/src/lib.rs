@@ -1,17 +1,53 @@
 use std::fs;
-use std::io::Cursor;
-use std::path::PathBuf;
+use std::io::{BufReader, Read};
+use std::path::{Path, PathBuf};
 use std::sync::atomic::{AtomicUsize, Ordering};
 
-use image::imageops::FilterType;
-use image::GenericImageView;
+use image::DynamicImage;
+use image::ImageDecoder;
+use image::ImageError;
 use image::ImageFormat;
-use imagequant::RGBA;
-use oxipng::{Deflater, InFile, Options, OutFile, StripChunks};
+use image::ImageReader;
 use rayon::prelude::*;
 use serde::{Deserialize, Serialize};
 use tauri::{AppHandle, Emitter};
 
+mod animation;
+mod color;
+mod depth;
+mod error;
+mod gif_output;
+mod i18n;
+mod icons;
+mod jpeg_optimize;
+mod jpeg_output;
+mod jxl;
+mod manifest;
+mod palette;
+mod pipeline;
+mod placeholder;
+mod png_optimize;
+mod probe;
+mod quantize;
+mod stage;
+mod svg;
+mod webp_output;
+
+pub use animation::AnimationOptions;
+pub use color::{ColorAnalysis, PaletteColor};
+pub use error::{ErrorKind, ProcessError, Warning, WarningKind};
+pub use i18n::Locale;
+pub use icons::{IconFile, IconOptions, IconSetResult};
+pub use jpeg_optimize::JpegOptimizeOptions;
+pub use jxl::JxlOptions;
+pub use palette::BatchPaletteOptions;
+pub use pipeline::{PipelineStage, Step, StepResult};
+pub use placeholder::{Placeholder, PlaceholderHash, PlaceholderOptions};
+pub use png_optimize::{Interlacing, OxipngOptions};
+pub use quantize::{QualityFallback, QuantizeOptions};
+pub use stage::{register_stage, Stage, StageSpec};
+pub use webp_output::WebpOptions;
+
 // ============================================================================
 // データ構造
 // ============================================================================
@@ -24,6 +60,25 @@ pub struct ImageInfo {
     pub height: u32,
     pub size: u64,
     pub original_path: String,
+    // ファイル内容から判別したフォーマット ("png", "jpeg", "svg" など)
+    pub format: String,
+    // カラータイプ ("Rgba8" など)
+    pub color_type: String,
+    // 1 チャンネルあたりのビット深度
+    pub bit_depth: u8,
+    // フレーム数 (静止画は 1)
+    pub frame_count: u32,
+    // 色解析結果 (analyze_colors 指定時のみ)
+    pub colors: Option<ColorAnalysis>,
+}
+
+/// 画像情報の取得結果
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ImageInfoResult {
+    // 読み取れた画像 (色解析のみ失敗した場合は色解析結果なしで含める)
+    pub images: Vec<ImageInfo>,
+    // 読み取れなかった・色解析に失敗したファイルのエラー
+    pub errors: Vec<ProcessError>,
 }
 
 /// リサイズオプション
@@ -38,14 +93,54 @@ pub struct ResizeOptions {
 #[derive(Debug, Clone, Deserialize)]
 pub struct QuantOptions {
     pub quality: u8,
+    // ディザリング・速度・色数などの詳細設定
+    #[serde(default, flatten)]
+    pub settings: QuantizeOptions,
 }
 
 /// 出力フォーマット
-#[derive(Debug, Clone, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
 #[serde(rename_all = "lowercase")]
 pub enum OutputFormat {
+    #[default]
     Png,
     Webp,
+    Gif,
+    Jxl,
+    Jpeg,
+}
+
+impl OutputFormat {
+    /// 出力ファイルの拡張子
+    fn extension(&self) -> &'static str {
+        match self {
+            OutputFormat::Png => "png",
+            OutputFormat::Webp => "webp",
+            OutputFormat::Gif => "gif",
+            OutputFormat::Jxl => "jxl",
+            OutputFormat::Jpeg => "jpg",
+        }
+    }
+
+    /// MIME タイプ
+    fn mime_type(&self) -> &'static str {
+        match self {
+            OutputFormat::Png => "image/png",
+            OutputFormat::Webp => "image/webp",
+            OutputFormat::Gif => "image/gif",
+            OutputFormat::Jxl => "image/jxl",
+            OutputFormat::Jpeg => "image/jpeg",
+        }
+    }
+}
+
+/// 出力バリアント (サイズ × フォーマット × クオリティ)
+#[derive(Debug, Clone, Deserialize)]
+pub struct OutputVariant {
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub format: OutputFormat,
+    pub quality: u8,
 }
 
 /// 一括処理オプション
@@ -59,22 +154,135 @@ pub struct ProcessOptions {
     // pngquant 設定 (PNG のみ)
     pub quantize_enabled: bool,
     pub quality: u8,
-    // oxipng 最適化設定 (PNG のみ)
+    // 減色の詳細設定 (ディザリング・速度・色数など、GIF・APNG の共通パレットにも適用)
+    #[serde(default)]
+    pub quantize: QuantizeOptions,
+    // 最適化設定 (PNG は oxipng、JPEG はハフマン符号の最適化)
     pub optimize_enabled: bool,
+    // oxipng の詳細設定 (プリセット・Zopfli・インターレースなど)
+    #[serde(default)]
+    pub oxipng: OxipngOptions,
     // 出力先ディレクトリ (None の場合は元ファイルと同じ場所)
     pub output_dir: Option<String>,
     // 出力フォーマット
     pub output_format: OutputFormat,
+    // 出力バリアント (指定時はリサイズ設定・出力フォーマットの代わりに使用)
+    #[serde(default)]
+    pub variants: Vec<OutputVariant>,
+    // レスポンシブ画像マニフェスト (JSON) の出力先 (None の場合は出力しない)
+    #[serde(default)]
+    pub manifest_path: Option<String>,
+    // プレースホルダー生成設定 (None の場合は生成しない)
+    #[serde(default)]
+    pub placeholder: Option<PlaceholderOptions>,
+    // アニメーション設定 (既定で有効、None の場合は先頭フレームのみを静止画として処理)
+    #[serde(default = "default_animation")]
+    pub animation: Option<AnimationOptions>,
+    // WebP 出力設定
+    #[serde(default)]
+    pub webp: WebpOptions,
+    // JPEG XL 出力設定
+    #[serde(default)]
+    pub jxl: JxlOptions,
+    // JPEG 最適化設定 (JPEG 入力は再エンコードせずに最適化する)
+    #[serde(default)]
+    pub jpeg: JpegOptimizeOptions,
+    // 16 ビット入力をロスレス PNG 出力 (pngquant 無効) のとき 16 ビットのまま保持する
+    #[serde(default)]
+    pub keep_16bit: bool,
+    // pngquant が最低クオリティを満たせなかったときの対応
+    #[serde(default)]
+    pub quality_fallback: QualityFallback,
+    // 一括処理の全画像で共通のパレットを使う (pngquant 有効時のみ、None の場合は画像ごとに生成)
+    #[serde(default)]
+    pub batch_palette: Option<BatchPaletteOptions>,
+    // リサイズ後に順に適用する変換ステージ (組み込みまたは register_stage で登録したもの)
+    #[serde(default)]
+    pub stages: Vec<StageSpec>,
+    // メッセージ・警告の表示言語
+    #[serde(default)]
+    pub locale: Locale,
 }
 
-/// 処理結果
+impl Default for ProcessOptions {
+    fn default() -> Self {
+        ProcessOptions {
+            resize_enabled: false,
+            width: None,
+            height: None,
+            maintain_aspect_ratio: true,
+            quantize_enabled: false,
+            quality: 80,
+            quantize: QuantizeOptions::default(),
+            optimize_enabled: false,
+            oxipng: OxipngOptions::default(),
+            output_dir: None,
+            output_format: OutputFormat::default(),
+            variants: Vec::new(),
+            manifest_path: None,
+            placeholder: None,
+            animation: default_animation(),
+            webp: WebpOptions::default(),
+            jxl: JxlOptions::default(),
+            jpeg: JpegOptimizeOptions::default(),
+            keep_16bit: false,
+            quality_fallback: QualityFallback::default(),
+            batch_palette: None,
+            stages: Vec::new(),
+            locale: Locale::default(),
+        }
+    }
+}
+
+fn default_animation() -> Option<AnimationOptions> {
+    Some(AnimationOptions::default())
+}
+
+/// 出力ファイル 1 件分の情報
 #[derive(Debug, Clone, Serialize)]
+pub struct OutputEntry {
+    pub output_path: String,
+    pub width: u32,
+    pub height: u32,
+    pub format: OutputFormat,
+    pub size: u64,
+}
+
+/// 処理結果
+#[derive(Debug, Clone, Default, Serialize)]
 pub struct ProcessResult {
     pub success: bool,
     pub original_size: u64,
     pub result_size: u64,
     pub output_path: String,
     pub message: String,
+    // 生成した出力ファイル一覧 (バリアント指定時は複数)
+    pub outputs: Vec<OutputEntry>,
+    // 遅延読み込み用プレースホルダー
+    pub placeholder: Option<Placeholder>,
+    // 処理は成功したが注意が必要な事項 (拡張子と内容の不一致など)
+    pub warnings: Vec<Warning>,
+    // 元画像の 1 チャンネルあたりのビット深度 (不明な場合は 0)
+    pub source_bit_depth: u8,
+    // 最低クオリティ不足で代替処理を行った場合、その内容
+    pub quality_fallback: Option<QualityFallback>,
+    // 失敗時のエラー (code で種類を判別できる)
+    pub error: Option<ProcessError>,
+    // ステージごとの処理内容・所要時間・処理後のサイズ
+    pub steps: Vec<StepResult>,
+}
+
+impl ProcessResult {
+    /// 失敗時の結果を生成 (メッセージは指定した言語で表示する)
+    fn failure(original_size: u64, error: ProcessError, locale: Locale) -> Self {
+        ProcessResult {
+            success: false,
+            original_size,
+            message: error.message(locale),
+            error: Some(error),
+            ..Default::default()
+        }
+    }
 }
 
 /// 進捗イベントのペイロード
@@ -86,27 +294,87 @@ pub struct ProgressPayload {
     pub result: Option<ProcessResult>,
 }
 
+/// 完了イベントのペイロード
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct CompletePayload {
+    // 画像ごとの結果に含まれない一括処理全体のエラー (マニフェストの書き出しなど)
+    pub errors: Vec<ProcessError>,
+}
+
 // ============================================================================
 // ヘルパー関数
 // ============================================================================
 
-/// 画像が PNG かどうかを拡張子で判定
-fn is_png(path: &PathBuf) -> bool {
-    path.extension()
-        .and_then(|ext| ext.to_str())
-        .map(|ext| ext.eq_ignore_ascii_case("png"))
-        .unwrap_or(false)
+/// serde の既定値として true を返す (各オプション共通)
+fn default_true() -> bool {
+    true
+}
+
+/// ファイル先頭のマジックバイトから画像フォーマットを判定
+fn detect_format(path: &Path) -> Option<ImageFormat> {
+    let mut header = Vec::with_capacity(32);
+    fs::File::open(path)
+        .and_then(|f| f.take(32).read_to_end(&mut header))
+        .ok()?;
+    image::guess_format(&header).ok()
 }
 
-/// PNG 以外の画像を PNG に変換してバイト列として返す
-fn convert_to_png(path: &PathBuf) -> Result<Vec<u8>, String> {
-    let img = image::open(path).map_err(|e| format!("画像を開けません: {}", e))?;
+/// 拡張子と内容のフォーマットが食い違っている場合の警告
+fn extension_warning(path: &Path, format: Option<ImageFormat>) -> Option<WarningKind> {
+    let format = format?;
+    let ext = path.extension().and_then(|ext| ext.to_str())?;
+    if format
+        .extensions_str()
+        .iter()
+        .any(|e| e.eq_ignore_ascii_case(ext))
+    {
+        return None;
+    }
+    Some(WarningKind::ExtensionMismatch {
+        extension: ext.to_string(),
+        format: probe::format_name(format),
+    })
+}
+
+/// ファイル内容からフォーマットを判別して画像を開く (拡張子には依存しない)
+/// SVG は本来の寸法でラスタライズする
+fn open_image(path: &Path) -> Result<DynamicImage, ErrorKind> {
+    if svg::is_svg(path) {
+        return svg::load(path)
+            .and_then(|tree| svg::render_intrinsic(&tree))
+            .map_err(|reason| ErrorKind::DecodeFailed { reason });
+    }
+    open_reader(path)?.decode().map_err(image_error)
+}
+
+/// ヘッダーのみ読み取り、寸法とデコード後の 1 チャンネルあたりのビット深度を返す (SVG は除く)
+fn read_header(path: &Path) -> Result<(u32, u32, u8), ErrorKind> {
+    let decoder = open_reader(path)?.into_decoder().map_err(image_error)?;
+    let (width, height) = decoder.dimensions();
+    let color = decoder.color_type();
+    let bit_depth = (color.bits_per_pixel() / color.channel_count().max(1) as u16) as u8;
+    Ok((width, height, bit_depth))
+}
 
-    let mut png_data = Cursor::new(Vec::new());
-    img.write_to(&mut png_data, ImageFormat::Png)
-        .map_err(|e| format!("PNG への変換に失敗: {}", e))?;
+/// 内容からフォーマットを判別したリーダーを開く
+fn open_reader(path: &Path) -> Result<ImageReader<BufReader<fs::File>>, ErrorKind> {
+    ImageReader::open(path)
+        .and_then(|r| r.with_guessed_format())
+        .map_err(|e| ErrorKind::DecodeFailed {
+            reason: e.to_string(),
+        })
+}
 
-    Ok(png_data.into_inner())
+/// デコードエラーを未対応形式とそれ以外に分ける
+fn image_error(e: ImageError) -> ErrorKind {
+    match e {
+        ImageError::Unsupported(_) => ErrorKind::UnsupportedFormat {
+            reason: e.to_string(),
+        },
+        _ => ErrorKind::DecodeFailed {
+            reason: e.to_string(),
+        },
+    }
 }
 
 /// 新しい寸法を計算するヘルパー関数
@@ -158,233 +426,149 @@ fn greet(name: &str) -> String {
     format!("Hello, {}! You've been greeted from Rust!", name)
 }
 
-/// 画像情報を取得する
-#[tauri::command]
-fn get_image_info(paths: Vec<String>) -> Result<Vec<ImageInfo>, String> {
-    let mut results = Vec::new();
+/// 1 ファイル分の画像情報を取得する (ヘッダーのみ読み取り)
+/// 色解析のみ失敗した場合は、色解析結果なしの情報とエラーの両方を返す
+fn read_image_info(
+    path_str: &str,
+    analyze_colors: bool,
+) -> (Option<ImageInfo>, Option<ProcessError>) {
+    let path = PathBuf::from(path_str);
+    let error = |kind| Some(ProcessError::new(path_str, kind));
+
+    if !path.exists() {
+        return (None, error(ErrorKind::NotFound));
+    }
 
-    for path_str in &paths {
-        let path = PathBuf::from(path_str);
+    // ヘッダーを読み取る (ピクセルはデコードしない)
+    let info = match probe::probe(&path) {
+        Ok(i) => i,
+        Err(kind) => return (None, error(kind)),
+    };
 
-        if !path.exists() {
-            continue;
-        }
+    // ファイルサイズを取得
+    let size = fs::metadata(&path).map(|m| m.len()).unwrap_or(0);
 
-        // 画像を開く
-        let img = match image::open(&path) {
-            Ok(i) => i,
-            Err(e) => {
-                eprintln!("{}: 画像を開けません - {}", path_str, e);
-                continue;
-            }
-        };
-
-        let (width, height) = img.dimensions();
-
-        // ファイルサイズを取得
-        let size = fs::metadata(&path).map(|m| m.len()).unwrap_or(0);
-
-        results.push(ImageInfo {
-            name: path
-                .file_name()
-                .and_then(|s| s.to_str())
-                .unwrap_or("unknown")
-                .to_string(),
-            width,
-            height,
-            size,
-            original_path: path_str.clone(),
+    // 色解析 (全ピクセルが必要なため指定時のみデコード)
+    let (colors, color_error) = if analyze_colors {
+        let analyzed = open_image(&path).and_then(|img| {
+            color::analyze(&img).map_err(|reason| ErrorKind::TransformFailed { reason })
         });
-    }
+        match analyzed {
+            Ok(c) => (Some(c), None),
+            Err(kind) => (None, error(kind)),
+        }
+    } else {
+        (None, None)
+    };
+
+    let info = ImageInfo {
+        name: path
+            .file_name()
+            .and_then(|s| s.to_str())
+            .unwrap_or("unknown")
+            .to_string(),
+        width: info.width,
+        height: info.height,
+        size,
+        original_path: path_str.to_string(),
+        format: info.format,
+        color_type: info.color_type,
+        bit_depth: info.bit_depth,
+        frame_count: info.frame_count,
+        colors,
+    };
+    (Some(info), color_error)
+}
 
-    Ok(results)
+/// 画像情報を取得する (並列処理)
+/// analyze_colors 指定時は代表色・パレット・アルファ/グレースケール判定も行う
+/// 読み取れなかったファイルはファイルごとのエラーとして返す
+#[tauri::command]
+fn get_image_info(
+    paths: Vec<String>,
+    analyze_colors: Option<bool>,
+) -> Result<ImageInfoResult, ProcessError> {
+    let analyze_colors = analyze_colors.unwrap_or(false);
+
+    let results: Vec<(Option<ImageInfo>, Option<ProcessError>)> = paths
+        .par_iter()
+        .map(|path_str| read_image_info(path_str, analyze_colors))
+        .collect();
+
+    let mut result = ImageInfoResult::default();
+    for (info, error) in results {
+        result.images.extend(info);
+        result.errors.extend(error);
+    }
+    Ok(result)
 }
 
 /// PNG 最適化 (oxipng)
+/// options 省略時はプリセット 4・libdeflater レベル 12
 #[tauri::command]
-fn optimize_images(paths: Vec<String>) -> Result<Vec<ProcessResult>, String> {
-    let mut options = Options::from_preset(4);
-    options.deflater = Deflater::Libdeflater { compression: 12 };
-    options.strip = StripChunks::Safe;
-    options.optimize_alpha = true;
-    options.fast_evaluation = true;
-
-    let mut results = Vec::new();
-
-    for path_str in &paths {
-        let path = PathBuf::from(path_str);
-
-        if !path.exists() {
-            results.push(ProcessResult {
-                success: false,
-                original_size: 0,
-                result_size: 0,
-                output_path: String::new(),
-                message: format!("{}: ファイルが存在しません", path_str),
-            });
-            continue;
-        }
+fn optimize_images(
+    paths: Vec<String>,
+    options: Option<OxipngOptions>,
+    locale: Option<Locale>,
+) -> Result<Vec<ProcessResult>, ProcessError> {
+    // PNG 入力は元データのまま、それ以外はロスレスで PNG に変換して最適化する
+    let options = ProcessOptions {
+        optimize_enabled: true,
+        oxipng: options.unwrap_or_default(),
+        keep_16bit: true,
+        locale: locale.unwrap_or_default(),
+        ..Default::default()
+    };
 
-        let original_size = fs::metadata(&path).map(|m| m.len()).unwrap_or(0);
+    Ok(paths
+        .iter()
+        .map(|path_str| pipeline::run(path_str, &options, "optimized"))
+        .collect())
+}
 
-        let stem = path
-            .file_stem()
-            .and_then(|s| s.to_str())
-            .unwrap_or("output");
-        let parent = path.parent().unwrap_or(std::path::Path::new("."));
-        let output_path = parent.join(format!("{}_optimized.png", stem));
-
-        let output = OutFile::Path {
-            path: Some(output_path.clone()),
-            preserve_attrs: false,
-        };
-
-        let result = if is_png(&path) {
-            let input = InFile::Path(path.clone());
-            oxipng::optimize(&input, &output, &options)
-        } else {
-            match convert_to_png(&path) {
-                Ok(png_data) => {
-                    oxipng::optimize_from_memory(&png_data, &options).and_then(|optimized| {
-                        fs::write(&output_path, &optimized)
-                            .map_err(|e| oxipng::PngError::Other(e.to_string().into()))?;
-                        Ok((png_data.len(), optimized.len()))
-                    })
-                }
-                Err(e) => {
-                    results.push(ProcessResult {
-                        success: false,
-                        original_size,
-                        result_size: 0,
-                        output_path: String::new(),
-                        message: format!("{}: {}", path_str, e),
-                    });
-                    continue;
-                }
-            }
-        };
-
-        match result {
-            Ok((_, optimized_size)) => {
-                let result_size = fs::metadata(&output_path)
-                    .map(|m| m.len())
-                    .unwrap_or(optimized_size as u64);
-                results.push(ProcessResult {
-                    success: true,
-                    original_size,
-                    result_size,
-                    output_path: output_path.to_string_lossy().to_string(),
-                    message: format!(
-                        "{} → {} bytes ({:.1}% 削減)",
-                        original_size,
-                        result_size,
-                        if original_size > 0 {
-                            (1.0 - result_size as f64 / original_size as f64) * 100.0
-                        } else {
-                            0.0
-                        }
-                    ),
-                });
-            }
-            Err(e) => {
-                results.push(ProcessResult {
-                    success: false,
-                    original_size,
-                    result_size: 0,
-                    output_path: String::new(),
-                    message: format!("{}: 最適化に失敗しました - {}", path_str, e),
-                });
-            }
-        }
-    }
+/// JPEG 最適化 (JPEG 入力はデコード・再エンコードせずにメタデータ除去とハフマン最適化)
+/// JPEG 以外の入力は JPEG に変換して最適化する
+#[tauri::command]
+fn optimize_jpegs(
+    paths: Vec<String>,
+    options: JpegOptimizeOptions,
+    locale: Option<Locale>,
+) -> Result<Vec<ProcessResult>, ProcessError> {
+    let options = ProcessOptions {
+        optimize_enabled: true,
+        output_format: OutputFormat::Jpeg,
+        jpeg: options,
+        locale: locale.unwrap_or_default(),
+        ..Default::default()
+    };
 
-    Ok(results)
+    Ok(paths
+        .iter()
+        .map(|path_str| pipeline::run(path_str, &options, "optimized"))
+        .collect())
 }
 
 /// リサイズ処理
 #[tauri::command]
-fn resize_images(paths: Vec<String>, options: ResizeOptions) -> Result<Vec<ProcessResult>, String> {
-    let mut results = Vec::new();
-
-    for path_str in &paths {
-        let path = PathBuf::from(path_str);
-
-        if !path.exists() {
-            results.push(ProcessResult {
-                success: false,
-                original_size: 0,
-                result_size: 0,
-                output_path: String::new(),
-                message: format!("{}: ファイルが存在しません", path_str),
-            });
-            continue;
-        }
-
-        let img = match image::open(&path) {
-            Ok(i) => i,
-            Err(e) => {
-                results.push(ProcessResult {
-                    success: false,
-                    original_size: 0,
-                    result_size: 0,
-                    output_path: String::new(),
-                    message: format!("{}: 画像を開けません - {}", path_str, e),
-                });
-                continue;
-            }
-        };
-
-        let original_size = fs::metadata(&path).map(|m| m.len()).unwrap_or(0);
-        let (orig_w, orig_h) = img.dimensions();
-
-        // 新しいサイズを計算
-        let (new_width, new_height) = calculate_new_dimensions(
-            orig_w,
-            orig_h,
-            options.width,
-            options.height,
-            options.maintain_aspect_ratio,
-        );
-
-        // リサイズ実行 (Lanczos3 フィルタ使用)
-        let resized = img.resize_exact(new_width, new_height, FilterType::Lanczos3);
-
-        // 出力パスを生成
-        let stem = path
-            .file_stem()
-            .and_then(|s| s.to_str())
-            .unwrap_or("output");
-        let parent = path.parent().unwrap_or(std::path::Path::new("."));
-        let output_path = parent.join(format!("{}_resized.png", stem));
-
-        // 保存
-        match resized.save(&output_path) {
-            Ok(_) => {
-                let result_size = fs::metadata(&output_path).map(|m| m.len()).unwrap_or(0);
-                results.push(ProcessResult {
-                    success: true,
-                    original_size,
-                    result_size,
-                    output_path: output_path.to_string_lossy().to_string(),
-                    message: format!(
-                        "{}x{} → {}x{} にリサイズしました",
-                        orig_w, orig_h, new_width, new_height
-                    ),
-                });
-            }
-            Err(e) => {
-                results.push(ProcessResult {
-                    success: false,
-                    original_size,
-                    result_size: 0,
-                    output_path: String::new(),
-                    message: format!("保存エラー: {}", e),
-                });
-            }
-        }
-    }
+fn resize_images(
+    paths: Vec<String>,
+    options: ResizeOptions,
+    locale: Option<Locale>,
+) -> Result<Vec<ProcessResult>, ProcessError> {
+    let options = ProcessOptions {
+        resize_enabled: true,
+        width: options.width,
+        height: options.height,
+        maintain_aspect_ratio: options.maintain_aspect_ratio,
+        keep_16bit: true,
+        locale: locale.unwrap_or_default(),
+        ..Default::default()
+    };
 
-    Ok(results)
+    Ok(paths
+        .iter()
+        .map(|path_str| pipeline::run(path_str, &options, "resized"))
+        .collect())
 }
 
 /// pngquant 圧縮 (imagequant)
@@ -392,507 +576,91 @@ fn resize_images(paths: Vec<String>, options: ResizeOptions) -> Result<Vec<Proce
 fn quantize_images(
     paths: Vec<String>,
     options: QuantOptions,
-) -> Result<Vec<ProcessResult>, String> {
-    let mut results = Vec::new();
-
-    for path_str in &paths {
-        let path = PathBuf::from(path_str);
-
-        if !path.exists() {
-            results.push(ProcessResult {
-                success: false,
-                original_size: 0,
-                result_size: 0,
-                output_path: String::new(),
-                message: format!("{}: ファイルが存在しません", path_str),
-            });
-            continue;
-        }
-
-        // 画像を読み込み
-        let img = match image::open(&path) {
-            Ok(i) => i.to_rgba8(),
-            Err(e) => {
-                results.push(ProcessResult {
-                    success: false,
-                    original_size: 0,
-                    result_size: 0,
-                    output_path: String::new(),
-                    message: format!("画像読み込みエラー: {}", e),
-                });
-                continue;
-            }
-        };
-
-        let original_size = fs::metadata(&path).map(|m| m.len()).unwrap_or(0);
-        let (width, height) = img.dimensions();
-
-        // RGBA ピクセルデータを取得
-        let pixels: Vec<RGBA> = img
-            .pixels()
-            .map(|p| RGBA::new(p[0], p[1], p[2], p[3]))
-            .collect();
-
-        // imagequant で量子化
-        let mut attrs = imagequant::new();
-
-        // クオリティ設定 (min, max)
-        let min_quality = (options.quality as u32).saturating_sub(10).max(0);
-        let max_quality = options.quality as u32;
-        if let Err(e) = attrs.set_quality(min_quality as u8, max_quality as u8) {
-            results.push(ProcessResult {
-                success: false,
-                original_size,
-                result_size: 0,
-                output_path: String::new(),
-                message: format!("クオリティ設定エラー: {:?}", e),
-            });
-            continue;
-        }
-
-        let mut liq_image =
-            match attrs.new_image(pixels.as_slice(), width as usize, height as usize, 0.0) {
-                Ok(img) => img,
-                Err(e) => {
-                    results.push(ProcessResult {
-                        success: false,
-                        original_size,
-                        result_size: 0,
-                        output_path: String::new(),
-                        message: format!("画像作成エラー: {:?}", e),
-                    });
-                    continue;
-                }
-            };
-
-        let mut quantized = match attrs.quantize(&mut liq_image) {
-            Ok(q) => q,
-            Err(e) => {
-                results.push(ProcessResult {
-                    success: false,
-                    original_size,
-                    result_size: 0,
-                    output_path: String::new(),
-                    message: format!("量子化エラー: {:?}", e),
-                });
-                continue;
-            }
-        };
-
-        let _ = quantized.set_dithering_level(1.0);
-
-        let (palette, indexed_pixels) = match quantized.remapped(&mut liq_image) {
-            Ok(result) => result,
-            Err(e) => {
-                results.push(ProcessResult {
-                    success: false,
-                    original_size,
-                    result_size: 0,
-                    output_path: String::new(),
-                    message: format!("リマップエラー: {:?}", e),
-                });
-                continue;
-            }
-        };
-
-        // 出力パスを生成
-        let stem = path
-            .file_stem()
-            .and_then(|s| s.to_str())
-            .unwrap_or("output");
-        let parent = path.parent().unwrap_or(std::path::Path::new("."));
-        let output_path = parent.join(format!("{}_quantized.png", stem));
-
-        // lodepng で PNG として保存
-        let mut encoder = lodepng::Encoder::new();
-
-        // パレットを設定
-        for color in &palette {
-            if let Err(e) = encoder.info_raw_mut().palette_add(lodepng::RGBA {
-                r: color.r,
-                g: color.g,
-                b: color.b,
-                a: color.a,
-            }) {
-                results.push(ProcessResult {
-                    success: false,
-                    original_size,
-                    result_size: 0,
-                    output_path: String::new(),
-                    message: format!("パレット追加エラー: {:?}", e),
-                });
-                continue;
-            }
-            if let Err(e) = encoder.info_png_mut().color.palette_add(lodepng::RGBA {
-                r: color.r,
-                g: color.g,
-                b: color.b,
-                a: color.a,
-            }) {
-                results.push(ProcessResult {
-                    success: false,
-                    original_size,
-                    result_size: 0,
-                    output_path: String::new(),
-                    message: format!("パレット追加エラー: {:?}", e),
-                });
-                continue;
-            }
-        }
-
-        encoder.info_raw_mut().colortype = lodepng::ColorType::PALETTE;
-        encoder.info_raw_mut().set_bitdepth(8);
-        encoder.info_png_mut().color.colortype = lodepng::ColorType::PALETTE;
-        encoder.info_png_mut().color.set_bitdepth(8);
-
-        let png_data = match encoder.encode(&indexed_pixels, width as usize, height as usize) {
-            Ok(data) => data,
-            Err(e) => {
-                results.push(ProcessResult {
-                    success: false,
-                    original_size,
-                    result_size: 0,
-                    output_path: String::new(),
-                    message: format!("PNG エンコードエラー: {:?}", e),
-                });
-                continue;
-            }
-        };
-
-        if let Err(e) = fs::write(&output_path, &png_data) {
-            results.push(ProcessResult {
-                success: false,
-                original_size,
-                result_size: 0,
-                output_path: String::new(),
-                message: format!("ファイル書き込みエラー: {}", e),
-            });
-            continue;
-        }
-
-        let result_size = png_data.len() as u64;
-
-        results.push(ProcessResult {
-            success: true,
-            original_size,
-            result_size,
-            output_path: output_path.to_string_lossy().to_string(),
-            message: format!(
-                "クオリティ {} で圧縮: {} → {} bytes ({:.1}% 削減)",
-                options.quality,
-                original_size,
-                result_size,
-                if original_size > 0 {
-                    (1.0 - result_size as f64 / original_size as f64) * 100.0
-                } else {
-                    0.0
-                }
-            ),
-        });
-    }
+    locale: Option<Locale>,
+) -> Result<Vec<ProcessResult>, ProcessError> {
+    let mut options = ProcessOptions {
+        quantize_enabled: true,
+        quality: options.quality,
+        quantize: options.settings,
+        locale: locale.unwrap_or_default(),
+        ..Default::default()
+    };
+    palette::resolve(&mut options.quantize)
+        .map_err(|reason| ErrorKind::InvalidOptions { reason })?;
 
-    Ok(results)
+    Ok(paths
+        .iter()
+        .map(|path_str| pipeline::run(path_str, &options, "quantized"))
+        .collect())
 }
 
 /// 単一画像の処理（並列処理用）
 fn process_single_image(path_str: &str, options: &ProcessOptions) -> ProcessResult {
-    let path = PathBuf::from(path_str);
-
-    if !path.exists() {
-        return ProcessResult {
-            success: false,
-            original_size: 0,
-            result_size: 0,
-            output_path: String::new(),
-            message: format!("{}: ファイルが存在しません", path_str),
-        };
-    }
-
-    let original_size = fs::metadata(&path).map(|m| m.len()).unwrap_or(0);
-    let stem = path
-        .file_stem()
-        .and_then(|s| s.to_str())
-        .unwrap_or("output");
-
-    // 出力先ディレクトリを決定
-    let output_parent = if let Some(ref out_dir) = options.output_dir {
-        let out_path = PathBuf::from(out_dir);
-        // ディレクトリが存在しない場合は作成
-        if !out_path.exists() {
-            if let Err(e) = fs::create_dir_all(&out_path) {
-                return ProcessResult {
-                    success: false,
-                    original_size,
-                    result_size: 0,
-                    output_path: String::new(),
-                    message: format!("出力ディレクトリ作成エラー: {}", e),
-                };
-            }
-        }
-        out_path
-    } else {
-        path.parent()
-            .unwrap_or(std::path::Path::new("."))
-            .to_path_buf()
-    };
-
-    // 画像を読み込み
-    let mut img = match image::open(&path) {
-        Ok(i) => i,
-        Err(e) => {
-            return ProcessResult {
-                success: false,
-                original_size,
-                result_size: 0,
-                output_path: String::new(),
-                message: format!("{}: 画像を開けません - {}", path_str, e),
-            };
-        }
-    };
-
-    let mut process_steps: Vec<String> = Vec::new();
-    let (orig_w, orig_h) = img.dimensions();
-
-    // ステップ 1: リサイズ
-    if options.resize_enabled && (options.width.is_some() || options.height.is_some()) {
-        let (new_width, new_height) = calculate_new_dimensions(
-            orig_w,
-            orig_h,
-            options.width,
-            options.height,
-            options.maintain_aspect_ratio,
-        );
-        img = img.resize_exact(new_width, new_height, FilterType::Lanczos3);
-        process_steps.push(format!(
-            "リサイズ: {}x{} → {}x{}",
-            orig_w, orig_h, new_width, new_height
-        ));
-    }
-
-    // 出力フォーマットに応じて処理を分岐
-    let (final_data, extension) = match options.output_format {
-        OutputFormat::Png => {
-            // PNG 出力: pngquant → oxipng
-            let png_data: Vec<u8> = if options.quantize_enabled {
-                let rgba_img = img.to_rgba8();
-                let (width, height) = rgba_img.dimensions();
-
-                let pixels: Vec<RGBA> = rgba_img
-                    .pixels()
-                    .map(|p| RGBA::new(p[0], p[1], p[2], p[3]))
-                    .collect();
-
-                let mut attrs = imagequant::new();
-                let min_quality = (options.quality as u32).saturating_sub(10).max(0);
-                let max_quality = options.quality as u32;
-
-                if let Err(e) = attrs.set_quality(min_quality as u8, max_quality as u8) {
-                    return ProcessResult {
-                        success: false,
-                        original_size,
-                        result_size: 0,
-                        output_path: String::new(),
-                        message: format!("クオリティ設定エラー: {:?}", e),
-                    };
-                }
-
-                let mut liq_image =
-                    match attrs.new_image(pixels.as_slice(), width as usize, height as usize, 0.0) {
-                        Ok(img) => img,
-                        Err(e) => {
-                            return ProcessResult {
-                                success: false,
-                                original_size,
-                                result_size: 0,
-                                output_path: String::new(),
-                                message: format!("imagequant エラー: {:?}", e),
-                            };
-                        }
-                    };
-
-                let mut quantized = match attrs.quantize(&mut liq_image) {
-                    Ok(q) => q,
-                    Err(e) => {
-                        return ProcessResult {
-                            success: false,
-                            original_size,
-                            result_size: 0,
-                            output_path: String::new(),
-                            message: format!("量子化エラー: {:?}", e),
-                        };
-                    }
-                };
-
-                let _ = quantized.set_dithering_level(1.0);
-
-                let (palette, indexed_pixels) = match quantized.remapped(&mut liq_image) {
-                    Ok(result) => result,
-                    Err(e) => {
-                        return ProcessResult {
-                            success: false,
-                            original_size,
-                            result_size: 0,
-                            output_path: String::new(),
-                            message: format!("リマップエラー: {:?}", e),
-                        };
-                    }
-                };
-
-                let mut encoder = lodepng::Encoder::new();
-                for color in &palette {
-                    let _ = encoder.info_raw_mut().palette_add(lodepng::RGBA {
-                        r: color.r,
-                        g: color.g,
-                        b: color.b,
-                        a: color.a,
-                    });
-                    let _ = encoder.info_png_mut().color.palette_add(lodepng::RGBA {
-                        r: color.r,
-                        g: color.g,
-                        b: color.b,
-                        a: color.a,
-                    });
-                }
-
-                encoder.info_raw_mut().colortype = lodepng::ColorType::PALETTE;
-                encoder.info_raw_mut().set_bitdepth(8);
-                encoder.info_png_mut().color.colortype = lodepng::ColorType::PALETTE;
-                encoder.info_png_mut().color.set_bitdepth(8);
-
-                match encoder.encode(&indexed_pixels, width as usize, height as usize) {
-                    Ok(data) => {
-                        process_steps.push(format!("pngquant: クオリティ {}", options.quality));
-                        data
-                    }
-                    Err(e) => {
-                        return ProcessResult {
-                            success: false,
-                            original_size,
-                            result_size: 0,
-                            output_path: String::new(),
-                            message: format!("PNG エンコードエラー: {:?}", e),
-                        };
-                    }
-                }
-            } else {
-                // pngquant をスキップする場合は PNG に変換
-                let mut cursor = Cursor::new(Vec::new());
-                if let Err(e) = img.write_to(&mut cursor, ImageFormat::Png) {
-                    return ProcessResult {
-                        success: false,
-                        original_size,
-                        result_size: 0,
-                        output_path: String::new(),
-                        message: format!("PNG 変換エラー: {}", e),
-                    };
-                }
-                cursor.into_inner()
-            };
-
-            // oxipng 最適化
-            let optimized_data: Vec<u8> = if options.optimize_enabled {
-                let mut oxi_options = Options::from_preset(4);
-                oxi_options.deflater = Deflater::Libdeflater { compression: 12 };
-                oxi_options.strip = StripChunks::Safe;
-                oxi_options.optimize_alpha = true;
-                oxi_options.fast_evaluation = true;
-
-                match oxipng::optimize_from_memory(&png_data, &oxi_options) {
-                    Ok(optimized) => {
-                        process_steps.push("oxipng: 最適化".to_string());
-                        optimized
-                    }
-                    Err(e) => {
-                        return ProcessResult {
-                            success: false,
-                            original_size,
-                            result_size: 0,
-                            output_path: String::new(),
-                            message: format!("oxipng エラー: {}", e),
-                        };
-                    }
-                }
-            } else {
-                png_data
-            };
-
-            (optimized_data, "png")
-        }
-        OutputFormat::Webp => {
-            // WebP 出力
-            let rgba_img = img.to_rgba8();
-            let (width, height) = rgba_img.dimensions();
-
-            let webp_data = if options.quality >= 100 {
-                // ロスレス
-                process_steps.push("WebP: ロスレス".to_string());
-                let encoder = webp::Encoder::from_rgba(rgba_img.as_raw(), width, height);
-                encoder.encode_lossless().to_vec()
-            } else {
-                // ロッシー
-                process_steps.push(format!("WebP: クオリティ {}", options.quality));
-                let encoder = webp::Encoder::from_rgba(rgba_img.as_raw(), width, height);
-                encoder.encode(options.quality as f32).to_vec()
-            };
-
-            (webp_data, "webp")
-        }
-    };
-
-    // 最終出力ファイル名
-    let output_path = output_parent.join(format!("{}_processed.{}", stem, extension));
-
-    if let Err(e) = fs::write(&output_path, &final_data) {
-        return ProcessResult {
-            success: false,
-            original_size,
-            result_size: 0,
-            output_path: String::new(),
-            message: format!("ファイル書き込みエラー: {}", e),
-        };
-    }
-
-    let result_size = final_data.len() as u64;
-    let reduction = if original_size > 0 {
-        (1.0 - result_size as f64 / original_size as f64) * 100.0
-    } else {
-        0.0
-    };
-
-    ProcessResult {
-        success: true,
-        original_size,
-        result_size,
-        output_path: output_path.to_string_lossy().to_string(),
-        message: format!(
-            "{} | {} → {} bytes ({:.1}% 削減)",
-            process_steps.join(" → "),
-            original_size,
-            result_size,
-            reduction
-        ),
-    }
+    pipeline::run(path_str, options, "processed")
 }
 
 /// 一括処理: リサイズ → pngquant → oxipng の順で並列実行
+/// manifest_path 指定時は全件完了後にマニフェストを書き出す
 /// 別スレッドで実行することでUIをブロックしない
 #[tauri::command]
 fn process_images(
     app: AppHandle,
     paths: Vec<String>,
     options: ProcessOptions,
-) -> Result<Vec<ProcessResult>, String> {
+) -> Result<Vec<ProcessResult>, ProcessError> {
     let total = paths.len();
 
     // 処理を別スレッドで非同期実行し、結果は完了イベントで通知
     std::thread::spawn(move || {
         let completed = AtomicUsize::new(0);
+        let mut complete = CompletePayload::default();
+
+        // 共通パレット: 全画像のヒストグラムから先にパレットを作り、各画像の減色に使う
+        // 固定パレットが指定されている場合はそちらを優先する
+        let mut options = options;
+        let mut palette_error = palette::resolve(&mut options.quantize)
+            .err()
+            .map(|reason| ProcessError::from(ErrorKind::InvalidOptions { reason }));
+        if let Some(batch_palette) = options.batch_palette.clone() {
+            if options.quantize_enabled && options.quantize.palette.is_none() {
+                match palette::build(&paths, &options) {
+                    Ok(Some(shared)) => {
+                        // 最低クオリティを満たせなかったパレットは、最低クオリティ 0 で再試行する場合のみ使われる
+                        let used = !shared.below_min_quality
+                            || options.quality_fallback == QualityFallback::LowerQuality;
+                        if let Some(export_path) = batch_palette.export_path.filter(|_| used) {
+                            if let Err(reason) =
+                                palette::export(&shared.colors, Path::new(&export_path))
+                            {
+                                complete.errors.push(ProcessError::new(
+                                    &export_path,
+                                    ErrorKind::WriteFailed {
+                                        output_path: export_path.clone(),
+                                        reason,
+                                    },
+                                ));
+                            }
+                        }
+                        options.quantize.palette = Some(shared.colors);
+                        options.quantize.palette_below_min_quality = shared.below_min_quality;
+                    }
+                    // 読み込める画像がない (各ファイルのエラーとして報告される)
+                    Ok(None) => {}
+                    Err(reason) => {
+                        palette_error = Some(ErrorKind::QuantizeFailed { reason }.into())
+                    }
+                }
+            }
+        }
 
         // rayon による並列処理
-        let _results: Vec<ProcessResult> = paths
+        let results: Vec<ProcessResult> = paths
             .par_iter()
             .map(|path_str| {
-                let result = process_single_image(path_str, &options);
+                let result = match palette_error {
+                    Some(ref e) => ProcessResult::failure(0, e.clone(), options.locale),
+                    None => process_single_image(path_str, &options),
+                };
 
                 // 進捗カウント更新
                 let current = completed.fetch_add(1, Ordering::SeqCst) + 1;
@@ -912,14 +680,32 @@ fn process_images(
             })
             .collect();
 
-        // 処理完了をイベントで通知
-        let _ = app.emit("process-complete", ());
+        // マニフェスト出力 (結果は paths と同じ順序で並ぶ)
+        if let Some(ref manifest_path_str) = options.manifest_path {
+            let manifest_path = PathBuf::from(manifest_path_str);
+            let manifest = manifest::build(&paths, &results, &manifest_path);
+            if let Err(e) = manifest::write(&manifest, &manifest_path) {
+                complete
+                    .errors
+                    .push(ProcessError::new(manifest_path_str, e));
+            }
+        }
+
+        // 処理完了をイベントで通知 (一括処理全体のエラーも含める)
+        let _ = app.emit("process-complete", complete);
     });
 
     // すぐに返す（結果はイベントで送信される）
     Ok(vec![])
 }
 
+/// ファビコン・アプリアイコン一式を生成
+/// favicon.ico (16/32/48)・apple-touch-icon (180)・PWA アイコン (192/512)・site.webmanifest
+#[tauri::command]
+fn generate_icons(path: String, options: IconOptions) -> Result<IconSetResult, ProcessError> {
+    icons::generate(&PathBuf::from(path), &options)
+}
+
 // ============================================================================
 // アプリケーションエントリーポイント
 // ============================================================================
@@ -932,10 +718,12 @@ pub fn run() {
         .invoke_handler(tauri::generate_handler![
             greet,
             optimize_images,
+            optimize_jpegs,
             get_image_info,
             resize_images,
             quantize_images,
             process_images,
+            generate_icons,
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
@@ -0,0 +1,252 @@
+use std::cmp::Reverse;
+use std::fs;
+use std::path::{self, Component, Path, PathBuf};
+
+use serde::Serialize;
+
+use crate::error::ErrorKind;
+use crate::{OutputEntry, OutputFormat, Placeholder, ProcessResult};
+
+// ============================================================================
+// レスポンシブ画像マニフェスト
+// ============================================================================
+
+/// マニフェスト全体
+#[derive(Debug, Clone, Serialize)]
+pub struct Manifest {
+    pub images: Vec<ManifestEntry>,
+}
+
+/// 元画像 1 件分のエントリ
+#[derive(Debug, Clone, Serialize)]
+pub struct ManifestEntry {
+    pub source: String,
+    pub outputs: Vec<OutputEntry>,
+    pub placeholder: Option<Placeholder>,
+    // そのまま貼り付けられる <picture> マークアップ
+    pub html: String,
+}
+
+/// 処理結果からマニフェストを組み立てる (失敗した画像は含めない)
+pub fn build(paths: &[String], results: &[ProcessResult], manifest_path: &Path) -> Manifest {
+    let base_dir = manifest_path.parent().unwrap_or(Path::new("."));
+
+    let images = paths
+        .iter()
+        .zip(results)
+        .filter(|(_, result)| result.success && !result.outputs.is_empty())
+        .map(|(source, result)| ManifestEntry {
+            source: source.clone(),
+            outputs: result.outputs.clone(),
+            placeholder: result.placeholder.clone(),
+            html: picture_html(&result.outputs, base_dir),
+        })
+        .collect();
+
+    Manifest { images }
+}
+
+/// マニフェストを JSON として書き出す
+pub fn write(manifest: &Manifest, manifest_path: &Path) -> Result<(), ErrorKind> {
+    let write_failed = |e: &dyn std::fmt::Display| ErrorKind::WriteFailed {
+        output_path: manifest_path.to_string_lossy().to_string(),
+        reason: e.to_string(),
+    };
+    let json = serde_json::to_string_pretty(manifest).map_err(|e| write_failed(&e))?;
+
+    if let Some(parent) = manifest_path.parent() {
+        if !parent.as_os_str().is_empty() && !parent.exists() {
+            fs::create_dir_all(parent).map_err(|e| write_failed(&e))?;
+        }
+    }
+
+    fs::write(manifest_path, json).map_err(|e| write_failed(&e))
+}
+
+/// <picture> / srcset マークアップを生成 (PNG があれば <img> のフォールバックにする)
+fn picture_html(outputs: &[OutputEntry], base_dir: &Path) -> String {
+    let fallback_format = if outputs.iter().any(|o| o.format == OutputFormat::Png) {
+        OutputFormat::Png
+    } else {
+        outputs[outputs.len() - 1].format.clone()
+    };
+
+    // フォーマットごとに出現順でまとめる
+    let mut formats: Vec<OutputFormat> = Vec::new();
+    for output in outputs {
+        if !formats.contains(&output.format) {
+            formats.push(output.format.clone());
+        }
+    }
+
+    let mut html = String::from("<picture>\n");
+
+    for format in formats.iter().filter(|f| **f != fallback_format) {
+        html.push_str(&format!(
+            "  <source type=\"{}\" srcset=\"{}\" sizes=\"100vw\">\n",
+            format.mime_type(),
+            escape_attribute(&srcset(outputs, format, base_dir))
+        ));
+    }
+
+    // フォールバックは最大幅の出力を src にする
+    let fallback = outputs
+        .iter()
+        .filter(|o| o.format == fallback_format)
+        .max_by_key(|o| o.width)
+        .unwrap_or(&outputs[0]);
+
+    html.push_str(&format!(
+        "  <img src=\"{}\" srcset=\"{}\" sizes=\"100vw\" width=\"{}\" height=\"{}\" alt=\"\" loading=\"lazy\" decoding=\"async\">\n",
+        escape_attribute(&url(&fallback.output_path, base_dir)),
+        escape_attribute(&srcset(outputs, &fallback_format, base_dir)),
+        fallback.width,
+        fallback.height
+    ));
+    html.push_str("</picture>");
+
+    html
+}
+
+/// 指定フォーマットの srcset 文字列 ("a_320w.webp 320w, ...")
+/// 同じ幅の出力が複数ある場合は、幅の記述子が重複しないようファイルサイズが最大のもののみ使う
+fn srcset(outputs: &[OutputEntry], format: &OutputFormat, base_dir: &Path) -> String {
+    let mut entries: Vec<&OutputEntry> = outputs.iter().filter(|o| o.format == *format).collect();
+    entries.sort_by_key(|o| (o.width, Reverse(o.size)));
+    entries.dedup_by_key(|o| o.width);
+
+    entries
+        .iter()
+        .map(|o| format!("{} {}w", url(&o.output_path, base_dir), o.width))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// マニフェストの場所からの相対 URL (各セグメントをパーセントエンコードし、区切り文字は / に統一)
+fn url(output_path: &str, base_dir: &Path) -> String {
+    relative_path(Path::new(output_path), base_dir)
+        .components()
+        .map(|c| match c {
+            Component::ParentDir => "..".to_string(),
+            c => percent_encode(&c.as_os_str().to_string_lossy()),
+        })
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// マニフェストの場所からの相対パス
+/// 別のドライブにあるなど相対パスで表せない場合はファイル名のみにする
+fn relative_path(path: &Path, base_dir: &Path) -> PathBuf {
+    let absolute = |p: &Path| {
+        let p = if p.as_os_str().is_empty() {
+            Path::new(".")
+        } else {
+            p
+        };
+        path::absolute(p).unwrap_or_else(|_| p.to_path_buf())
+    };
+    let (path, base_dir) = (absolute(path), absolute(base_dir));
+
+    let mut path_components = path.components().peekable();
+    let mut base_components = base_dir.components().peekable();
+    if path_components.peek() != base_components.peek() {
+        return path.file_name().map(PathBuf::from).unwrap_or_default();
+    }
+    while path_components.peek().is_some() && path_components.peek() == base_components.peek() {
+        path_components.next();
+        base_components.next();
+    }
+
+    base_components
+        .map(|_| Component::ParentDir)
+        .chain(path_components)
+        .collect()
+}
+
+/// URL のパスセグメント用のパーセントエンコード (非予約文字以外はすべてエンコードする)
+fn percent_encode(segment: &str) -> String {
+    segment
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                (b as char).to_string()
+            }
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+/// HTML 属性値のエスケープ
+fn escape_attribute(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('"', "&quot;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn urls_are_relative_and_encoded() {
+        let base = Path::new("/site/public");
+        assert_eq!(url("/site/public/img/a b.png", base), "img/a%20b.png");
+        assert_eq!(
+            url("/site/assets/\"x\"&y.webp", base),
+            "../assets/%22x%22%26y.webp"
+        );
+        assert_eq!(url("/site/public/写真.png", base), "%E5%86%99%E7%9C%9F.png");
+    }
+
+    #[test]
+    fn relative_to_current_directory() {
+        assert_eq!(url("out/a.png", Path::new("")), "out/a.png");
+    }
+
+    #[test]
+    fn attributes_are_escaped() {
+        assert_eq!(escape_attribute("a\"b&c<d>"), "a&quot;b&amp;c&lt;d&gt;");
+    }
+
+    #[test]
+    fn srcset_candidates_survive_spaces() {
+        let entry = |name: &str, width| OutputEntry {
+            output_path: format!("/out/{}", name),
+            width,
+            height: width,
+            format: OutputFormat::Webp,
+            size: 0,
+        };
+        let outputs = [
+            entry("my photo_640w.webp", 640),
+            entry("my photo_320w.webp", 320),
+        ];
+        assert_eq!(
+            srcset(&outputs, &OutputFormat::Webp, Path::new("/out")),
+            "my%20photo_320w.webp 320w, my%20photo_640w.webp 640w"
+        );
+    }
+
+    #[test]
+    fn srcset_keeps_one_candidate_per_width() {
+        let entry = |name: &str, width, size| OutputEntry {
+            output_path: format!("/out/{}", name),
+            width,
+            height: width,
+            format: OutputFormat::Webp,
+            size,
+        };
+        let outputs = [
+            entry("a_320w.webp", 320, 100),
+            entry("a_320x320_q90.webp", 320, 300),
+            entry("a_320x320_q60.webp", 320, 200),
+            entry("a_640w.webp", 640, 400),
+        ];
+        assert_eq!(
+            srcset(&outputs, &OutputFormat::Webp, Path::new("/out")),
+            "a_320x320_q90.webp 320w, a_640w.webp 640w"
+        );
+    }
+}
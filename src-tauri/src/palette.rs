@@ -0,0 +1,334 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use imagequant::RGBA;
+use serde::Deserialize;
+
+use crate::i18n::Locale;
+use crate::quantize::{PaletteSource, QuantizeError};
+use crate::{depth, open_image, quantize, stage, ProcessOptions, QuantizeOptions};
+
+// ============================================================================
+// 固定パレットの読み込み
+// ============================================================================
+
+/// 固定パレットの指定があれば読み込み、減色先のパレットとして設定する
+pub fn resolve(options: &mut QuantizeOptions) -> Result<(), String> {
+    let Some(ref source) = options.fixed_palette else {
+        return Ok(());
+    };
+    let palette = load(source)?;
+    if palette.is_empty() {
+        return Err("fixed palette has no colors".to_string());
+    }
+    if palette.len() > 256 {
+        return Err(format!(
+            "fixed palette has too many colors ({}, max 256)",
+            palette.len()
+        ));
+    }
+    options.palette = Some(palette);
+    Ok(())
+}
+
+/// 指定からパレットを読み込む
+fn load(source: &PaletteSource) -> Result<Vec<RGBA>, String> {
+    match source {
+        PaletteSource::Colors(colors) => colors.iter().map(|c| parse_hex(c)).collect(),
+        PaletteSource::File(path_str) => {
+            let path = Path::new(path_str);
+            let extension = path
+                .extension()
+                .and_then(|e| e.to_str())
+                .map(|e| e.to_ascii_lowercase());
+            match extension.as_deref() {
+                Some("gpl") => parse_gpl(&read_to_string(path)?),
+                Some("act") => parse_act(&fs::read(path).map_err(|e| read_error(path, e))?),
+                Some("png") => from_image(path),
+                _ => read_to_string(path)?
+                    .lines()
+                    .map(str::trim)
+                    .filter(|line| !line.is_empty())
+                    .map(parse_hex)
+                    .collect(),
+            }
+        }
+    }
+}
+
+fn read_error(path: &Path, e: std::io::Error) -> String {
+    format!("{}: {}", path.display(), e)
+}
+
+fn read_to_string(path: &Path) -> Result<String, String> {
+    fs::read_to_string(path).map_err(|e| read_error(path, e))
+}
+
+/// 16 進カラーコード (#rgb / #rrggbb / #rrggbbaa、# は省略可)
+fn parse_hex(code: &str) -> Result<RGBA, String> {
+    let digits = code.trim().trim_start_matches('#');
+    let invalid = || format!("invalid color code: {}", code);
+    let expanded: String = match digits.len() {
+        3 | 4 => digits.chars().flat_map(|c| [c, c]).collect(),
+        6 | 8 => digits.to_string(),
+        _ => return Err(invalid()),
+    };
+    let channel = |i: usize| {
+        expanded
+            .get(i * 2..i * 2 + 2)
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+    };
+    let (r, g, b) = match (channel(0), channel(1), channel(2)) {
+        (Some(r), Some(g), Some(b)) => (r, g, b),
+        _ => return Err(invalid()),
+    };
+    let a = match expanded.len() {
+        8 => channel(3).ok_or_else(invalid)?,
+        _ => 255,
+    };
+    Ok(RGBA::new(r, g, b, a))
+}
+
+/// GIMP パレット (.gpl: ヘッダーの後に "R G B 名前" が 1 行 1 色)
+fn parse_gpl(text: &str) -> Result<Vec<RGBA>, String> {
+    let mut lines = text.lines();
+    if lines.next().map(str::trim) != Some("GIMP Palette") {
+        return Err("invalid GIMP palette header".to_string());
+    }
+    lines
+        .map(str::trim)
+        .filter(|line| {
+            !line.is_empty()
+                && !line.starts_with('#')
+                && !line.starts_with("Name:")
+                && !line.starts_with("Columns:")
+        })
+        .map(|line| {
+            let channels: Vec<u8> = line
+                .split_whitespace()
+                .take(3)
+                .filter_map(|v| v.parse().ok())
+                .collect();
+            match channels[..] {
+                [r, g, b] => Ok(RGBA::new(r, g, b, 255)),
+                _ => Err(format!("invalid GIMP palette line: {}", line)),
+            }
+        })
+        .collect()
+}
+
+/// Adobe Color Table (.act: 256 色 × RGB、末尾 4 バイトがあれば色数と透明色の番号)
+fn parse_act(data: &[u8]) -> Result<Vec<RGBA>, String> {
+    if data.len() < 768 {
+        return Err("invalid ACT file length".to_string());
+    }
+    let (count, transparent) = if data.len() >= 772 {
+        let count = u16::from_be_bytes([data[768], data[769]]) as usize;
+        let transparent = u16::from_be_bytes([data[770], data[771]]) as usize;
+        (if count == 0 { 256 } else { count.min(256) }, transparent)
+    } else {
+        (256, usize::MAX)
+    };
+    Ok(data[..count * 3]
+        .chunks_exact(3)
+        .enumerate()
+        .map(|(i, c)| RGBA::new(c[0], c[1], c[2], if i == transparent { 0 } else { 255 }))
+        .collect())
+}
+
+/// 参照用 PNG に含まれる色を出現順に集める
+fn from_image(path: &Path) -> Result<Vec<RGBA>, String> {
+    let img =
+        open_image(path).map_err(|e| format!("{}: {}", path.display(), e.message(Locale::En)))?;
+    let mut colors: Vec<RGBA> = Vec::new();
+    for p in img.to_rgba8().pixels() {
+        let color = RGBA::new(p[0], p[1], p[2], p[3]);
+        if !colors.contains(&color) {
+            if colors.len() == 256 {
+                return Err(format!(
+                    "{}: reference image has more than 256 colors",
+                    path.display()
+                ));
+            }
+            colors.push(color);
+        }
+    }
+    Ok(colors)
+}
+
+// ============================================================================
+// 一括処理の共通パレット
+// ============================================================================
+
+/// 共通パレット設定
+/// 全画像のヒストグラムから 1 つのパレットを作り、各画像をそのパレットにリマップする
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct BatchPaletteOptions {
+    // パレットの書き出し先 (拡張子で形式を決める: .gpl / .act / それ以外は 16 進カラーコードの一覧)
+    #[serde(default)]
+    pub export_path: Option<String>,
+}
+
+/// 一括処理の共通パレット
+#[derive(Debug, Clone)]
+pub struct SharedPalette {
+    pub colors: Vec<RGBA>,
+    // 最低クオリティを満たせず、最低クオリティ 0 で作り直した
+    pub below_min_quality: bool,
+}
+
+/// 一括処理の全画像から共通パレットを作る
+/// 追加ステージ適用後・リサイズ前の色を使う (アニメーションは先頭フレームのみ)
+/// デコードやステージに失敗したファイルは除外する (エラーは各ファイルの処理結果として報告される)
+/// 読み込めた画像が 1 枚もない場合は None
+pub fn build(paths: &[String], options: &ProcessOptions) -> Result<Option<SharedPalette>, String> {
+    let mut builder = quantize::PaletteBuilder::new(options.quality, &options.quantize)?;
+    let mut added = 0;
+    for path_str in paths {
+        let Ok(img) = open_image(&PathBuf::from(path_str)) else {
+            continue;
+        };
+        let Ok((img, _)) = stage::apply_all(img, &options.stages) else {
+            continue;
+        };
+        builder.add(&depth::to_rgba8(&img))?;
+        added += 1;
+    }
+    if added == 0 {
+        return Ok(None);
+    }
+
+    let shared_error = |e: QuantizeError| format!("shared palette: {}", e);
+    match builder.finish() {
+        Ok(colors) => Ok(Some(SharedPalette {
+            colors,
+            below_min_quality: false,
+        })),
+        // 代替処理は各画像で行うが、最低クオリティ 0 で再試行する場合に備えてパレットを作り直しておく
+        Err(QuantizeError::QualityTooLow) => {
+            builder.relax()?;
+            let colors = builder.finish().map_err(shared_error)?;
+            Ok(Some(SharedPalette {
+                colors,
+                below_min_quality: true,
+            }))
+        }
+        Err(e) => Err(shared_error(e)),
+    }
+}
+
+/// パレットをファイルに書き出す
+pub fn export(palette: &[RGBA], path: &Path) -> Result<(), String> {
+    let extension = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_ascii_lowercase());
+
+    let data = match extension.as_deref() {
+        Some("gpl") => to_gpl(palette).into_bytes(),
+        Some("act") => to_act(palette),
+        _ => palette
+            .iter()
+            .map(|c| format!("{}\n", hex(c)))
+            .collect::<String>()
+            .into_bytes(),
+    };
+
+    if let Some(parent) = path.parent() {
+        if !parent.as_os_str().is_empty() && !parent.exists() {
+            fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+    }
+    fs::write(path, data).map_err(|e| e.to_string())
+}
+
+/// #rrggbb (半透明の色は #rrggbbaa)
+fn hex(color: &RGBA) -> String {
+    if color.a == 255 {
+        format!("#{:02x}{:02x}{:02x}", color.r, color.g, color.b)
+    } else {
+        format!(
+            "#{:02x}{:02x}{:02x}{:02x}",
+            color.r, color.g, color.b, color.a
+        )
+    }
+}
+
+/// GIMP パレット (.gpl、アルファは保持されない)
+fn to_gpl(palette: &[RGBA]) -> String {
+    let mut gpl = format!(
+        "GIMP Palette\nName: Shared\nColumns: 16\n# {} colors\n",
+        palette.len()
+    );
+    for color in palette {
+        gpl.push_str(&format!(
+            "{:3} {:3} {:3}\t{}\n",
+            color.r,
+            color.g,
+            color.b,
+            hex(color)
+        ));
+    }
+    gpl
+}
+
+/// Adobe Color Table (.act: 256 色 × RGB + 色数 + 透明色の番号)
+fn to_act(palette: &[RGBA]) -> Vec<u8> {
+    let palette = &palette[..palette.len().min(256)];
+    let mut act = vec![0u8; 772];
+    for (i, color) in palette.iter().enumerate() {
+        act[i * 3..i * 3 + 3].copy_from_slice(&[color.r, color.g, color.b]);
+    }
+    act[768..770].copy_from_slice(&(palette.len() as u16).to_be_bytes());
+    let transparent = palette
+        .iter()
+        .position(|c| c.a == 0)
+        .map_or(0xFFFF, |i| i as u16);
+    act[770..772].copy_from_slice(&transparent.to_be_bytes());
+    act
+}
+
+#[cfg(test)]
+mod tests {
+    use image::{ImageBuffer, Rgb};
+
+    use super::*;
+
+    #[test]
+    fn shared_palette_skips_unreadable_files_and_relaxes_quality() {
+        let dir =
+            std::env::temp_dir().join(format!("image-optimizer-palette-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let gradient = dir.join("gradient.png");
+        ImageBuffer::from_fn(64, 64, |x, y| Rgb([x as u8 * 4, y as u8 * 4, 128u8]))
+            .save(&gradient)
+            .unwrap();
+        let broken = dir.join("broken.png");
+        fs::write(&broken, b"not an image").unwrap();
+
+        let paths: Vec<String> = [&broken, &gradient, &dir.join("missing.png")]
+            .iter()
+            .map(|p| p.to_string_lossy().to_string())
+            .collect();
+        let options = |min_quality| ProcessOptions {
+            quality: 100,
+            quantize: QuantizeOptions {
+                max_colors: 2,
+                min_quality: Some(min_quality),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        let shared = build(&paths, &options(0)).unwrap().unwrap();
+        assert!(!shared.below_min_quality);
+
+        // 2 色ではクオリティ 100 を満たせないため、最低クオリティ 0 で作り直す
+        let shared = build(&paths, &options(100)).unwrap().unwrap();
+        assert!(shared.below_min_quality);
+        assert!(!shared.colors.is_empty());
+
+        assert!(build(&paths[..1], &options(0)).unwrap().is_none());
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,1420 @@
+use std::borrow::Cow;
+use std::cell::OnceCell;
+use std::fs;
+use std::io::Cursor;
+use std::mem;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+use image::imageops::FilterType;
+use image::{DynamicImage, GenericImageView, ImageFormat, RgbaImage};
+use resvg::usvg;
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::animation::{self, Animation};
+use crate::error::{ErrorKind, ProcessError, WarningKind};
+use crate::i18n::{self, Locale};
+use crate::quantize::{self, QuantizeError};
+use crate::{
+    calculate_new_dimensions, depth, detect_format, extension_warning, gif_output, jpeg_optimize,
+    jpeg_output, jxl, open_image, placeholder, png_optimize, probe, read_header, stage, svg,
+    webp_output, JpegOptimizeOptions, OutputEntry, OutputFormat, OutputVariant, OxipngOptions,
+    ProcessOptions, ProcessResult, QualityFallback, QuantizeOptions, StageSpec, WebpOptions,
+};
+
+// ============================================================================
+// 処理パイプライン (デコード → 変換 (リサイズ・追加ステージ) → エンコード → 最適化 → 書き込み)
+// ============================================================================
+
+/// 処理ステップ
+/// 言語に依存しない形で記録し、表示時に render で指定の言語の文字列にする
+/// (シリアライズ時は {"name": ..., "params": {...}} になる)
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "name", content = "params", rename_all = "snake_case")]
+pub enum Step {
+    // 重複フレームの削除
+    DropDuplicates {
+        frames: usize,
+    },
+    // SVG を目標サイズで描画
+    Rasterize {
+        width: u32,
+        height: u32,
+    },
+    // リサイズ
+    Resize {
+        from: (u32, u32),
+        to: (u32, u32),
+    },
+    // 高ビット深度からディザリングで 8 ビットへ
+    Dither {
+        bit_depth: u8,
+    },
+    // 追加ステージ (description はステージが返した説明)
+    Stage {
+        name: String,
+        params: Value,
+        description: String,
+    },
+    // JPEG → JPEG XL のロスレス変換
+    JpegTranscode,
+    // PNG / JPEG の元データをそのまま使用
+    Passthrough,
+    // pngquant による減色
+    Quantize {
+        quality: u8,
+        options: QuantizeOptions,
+    },
+    // 最低クオリティ不足のためフルカラーで出力
+    TruecolorFallback,
+    // 最低クオリティ不足のため最低クオリティなしで再試行
+    QuantizeRetry {
+        quality: u8,
+        options: QuantizeOptions,
+    },
+    // WebP エンコード
+    Webp {
+        quality: u8,
+        options: WebpOptions,
+    },
+    // GIF エンコード
+    Gif {
+        quality: u8,
+        options: QuantizeOptions,
+    },
+    // JPEG XL エンコード (lossless の場合 distance は使わない)
+    Jxl {
+        lossless: bool,
+        distance: f32,
+        effort: u8,
+    },
+    // JPEG エンコード
+    Jpeg {
+        quality: u8,
+    },
+    // APNG エンコード (共通パレット使用時はそのクオリティと設定)
+    Apng {
+        frames: usize,
+        shared_palette: Option<(u8, QuantizeOptions)>,
+    },
+    // アニメーション WebP エンコード
+    AnimatedWebp {
+        frames: usize,
+        quality: u8,
+        options: WebpOptions,
+    },
+    // アニメーション GIF エンコード
+    AnimatedGif {
+        frames: usize,
+        quality: u8,
+        options: QuantizeOptions,
+    },
+    // アニメーション非対応の出力のため先頭フレームのみ使用
+    FirstFrameOnly {
+        frames: usize,
+    },
+    // oxipng による最適化
+    Oxipng {
+        options: OxipngOptions,
+    },
+    // JPEG のハフマン符号の最適化
+    JpegOptimize {
+        options: JpegOptimizeOptions,
+    },
+}
+
+impl Step {
+    /// 指定した言語の表示用文字列
+    pub fn render(&self, locale: Locale) -> String {
+        match self {
+            Step::DropDuplicates { frames } => match locale {
+                Locale::Ja => format!("重複フレーム削除: {} 枚", frames),
+                Locale::En => format!("Dropped duplicate frames: {}", frames),
+            },
+            Step::Rasterize { width, height } => format!(
+                "{}: {}x{}",
+                locale.pick("SVG ラスタライズ", "SVG rasterize"),
+                width,
+                height
+            ),
+            Step::Resize { from, to } => format!(
+                "{}: {}x{} → {}x{}",
+                locale.pick("リサイズ", "Resize"),
+                from.0,
+                from.1,
+                to.0,
+                to.1
+            ),
+            Step::Dither { bit_depth } => match locale {
+                Locale::Ja => format!("{} ビット → 8 ビット (ディザリング)", bit_depth),
+                Locale::En => format!("{}-bit → 8-bit (dithered)", bit_depth),
+            },
+            // 独自ステージの説明文は翻訳できないため、英語ではステージ名とパラメータを表示する
+            Step::Stage {
+                name,
+                params,
+                description,
+            } => stage::describe(name, params, locale).unwrap_or_else(|| match locale {
+                Locale::Ja => description.clone(),
+                Locale::En if params.is_null() => name.clone(),
+                Locale::En => format!("{} {}", name, params),
+            }),
+            Step::JpegTranscode => locale
+                .pick(
+                    "JPEG → JPEG XL: ロスレス変換 (復元可能)",
+                    "JPEG → JPEG XL: lossless transcode (reversible)",
+                )
+                .to_string(),
+            Step::Passthrough => locale
+                .pick(
+                    "元データを再エンコードせずに使用",
+                    "Original data used without re-encoding",
+                )
+                .to_string(),
+            Step::Quantize { quality, options } => {
+                format!("pngquant: {}", options.describe(*quality, locale))
+            }
+            Step::TruecolorFallback => locale
+                .pick(
+                    "pngquant: クオリティ不足のためフルカラーで出力",
+                    "pngquant: quality too low, kept full color",
+                )
+                .to_string(),
+            Step::QuantizeRetry { quality, options } => format!(
+                "pngquant: {} ({})",
+                locale.pick("クオリティ不足のため再試行", "quality too low, retried"),
+                options.describe(*quality, locale)
+            ),
+            Step::Webp { quality, options } => {
+                format!("WebP: {}", options.describe(*quality, locale))
+            }
+            Step::Gif { quality, options } => {
+                format!("GIF: {}", options.describe(*quality, locale))
+            }
+            Step::Jxl {
+                lossless: true,
+                effort,
+                ..
+            } => match locale {
+                Locale::Ja => format!("JPEG XL: ロスレス (努力値 {})", effort),
+                Locale::En => format!("JPEG XL: lossless (effort {})", effort),
+            },
+            Step::Jxl {
+                distance, effort, ..
+            } => match locale {
+                Locale::Ja => format!("JPEG XL: 距離 {:.2} (努力値 {})", distance, effort),
+                Locale::En => format!("JPEG XL: distance {:.2} (effort {})", distance, effort),
+            },
+            Step::Jpeg { quality } => match locale {
+                Locale::Ja => format!("JPEG: クオリティ {}", quality),
+                Locale::En => format!("JPEG: quality {}", quality),
+            },
+            Step::Apng {
+                frames,
+                shared_palette,
+            } => {
+                let frames = format!("APNG: {} {}", frames, locale.pick("フレーム", "frames"));
+                match shared_palette {
+                    Some((quality, options)) => format!(
+                        "{} ({}, {})",
+                        frames,
+                        locale.pick("共通パレット", "shared palette"),
+                        options.describe(*quality, locale)
+                    ),
+                    None => frames,
+                }
+            }
+            Step::AnimatedWebp {
+                frames,
+                quality,
+                options,
+            } => format!(
+                "{}: {} {} ({})",
+                locale.pick("アニメーション WebP", "Animated WebP"),
+                frames,
+                locale.pick("フレーム", "frames"),
+                options.describe(*quality, locale)
+            ),
+            Step::AnimatedGif {
+                frames,
+                quality,
+                options,
+            } => format!(
+                "{}: {} {} ({})",
+                locale.pick("アニメーション GIF", "Animated GIF"),
+                frames,
+                locale.pick("フレーム", "frames"),
+                options.describe(*quality, locale)
+            ),
+            Step::FirstFrameOnly { frames } => match locale {
+                Locale::Ja => format!("先頭フレームのみ ({} フレーム中)", frames),
+                Locale::En => format!("First frame only (of {})", frames),
+            },
+            Step::Oxipng { options } => format!("oxipng: {}", options.describe(locale)),
+            Step::JpegOptimize { options } if options.progressive => locale
+                .pick("JPEG 最適化: プログレッシブ", "JPEG optimize: progressive")
+                .to_string(),
+            Step::JpegOptimize { .. } => locale
+                .pick("JPEG 最適化: ハフマン符号", "JPEG optimize: Huffman tables")
+                .to_string(),
+        }
+    }
+}
+
+/// パイプラインのステージ
+#[derive(Debug, Clone, Copy, Serialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum PipelineStage {
+    Decode,
+    Transform,
+    Encode,
+    Optimize,
+    Write,
+}
+
+/// ステージ 1 件分の処理結果
+#[derive(Debug, Clone, Serialize)]
+pub struct StepResult {
+    // 対象の出力 (outputs のインデックス、デコードは全出力で共通のため None)
+    pub output: Option<usize>,
+    pub stage: PipelineStage,
+    // ステージ内で行った処理とそのパラメータ
+    pub operations: Vec<Step>,
+    // 所要時間 (ミリ秒)
+    pub duration_ms: f64,
+    // ステージ後のバイト数 (エンコード前は None)
+    pub size: Option<u64>,
+    // ステージ後の寸法
+    pub width: u32,
+    pub height: u32,
+}
+
+/// 処理ステップの記録
+#[derive(Debug)]
+pub struct StepLog {
+    // 各ステップ (表示時に render で文字列にする)
+    pub steps: Vec<Step>,
+    // 最低クオリティ不足で取った代替処理
+    pub quality_fallback: Option<QualityFallback>,
+    // 終了したステージの結果
+    pub stages: Vec<StepResult>,
+    // 現在のステージの開始時刻
+    stage_started: Instant,
+}
+
+impl Default for StepLog {
+    fn default() -> Self {
+        StepLog {
+            steps: Vec::new(),
+            quality_fallback: None,
+            stages: Vec::new(),
+            stage_started: Instant::now(),
+        }
+    }
+}
+
+impl StepLog {
+    pub fn push(&mut self, step: Step) {
+        self.steps.push(step);
+    }
+
+    /// 現在のステージを終了し、前のステージ終了後に記録したステップと所要時間を結果に残す
+    pub fn finish_stage(
+        &mut self,
+        stage: PipelineStage,
+        size: Option<u64>,
+        (width, height): (u32, u32),
+    ) {
+        let recorded: usize = self.stages.iter().map(|s| s.operations.len()).sum();
+        self.stages.push(StepResult {
+            output: None,
+            stage,
+            operations: self.steps[recorded..].to_vec(),
+            duration_ms: self.stage_started.elapsed().as_secs_f64() * 1000.0,
+            size,
+            width,
+            height,
+        });
+        self.stage_started = Instant::now();
+    }
+
+    /// 指定した言語で各ステップを " → " でつないだ文字列
+    pub fn render(&self, locale: Locale) -> String {
+        self.steps
+            .iter()
+            .map(|step| step.render(locale))
+            .collect::<Vec<_>>()
+            .join(" → ")
+    }
+}
+
+/// デコード済みの入力ファイル
+pub struct Source {
+    pub path: PathBuf,
+    // ファイル内容から判別したフォーマット
+    pub format: Option<ImageFormat>,
+    pub warnings: Vec<WarningKind>,
+    // アニメーション (オプション指定時かつ 2 フレーム以上の場合のみ)
+    pub animation: Option<Animation>,
+    // 削除した重複フレームの数
+    pub dropped_frames: usize,
+    // SVG のパース結果 (出力ごとに目標サイズでラスタライズする)
+    pub svg_tree: Option<usvg::Tree>,
+    // 元画像の寸法と 1 チャンネルあたりのビット深度 (ピクセルをデコードせずに判定に使う)
+    pub width: u32,
+    pub height: u32,
+    pub bit_depth: u8,
+    // 元画像 (アニメーションは先頭フレーム、SVG は本来の寸法で描画したもの)
+    // 静止画は再エンコードしない出力のみの場合デコード不要のため、必要な場合のみデコードステージで image() を呼んでデコードする
+    img: OnceCell<DynamicImage>,
+}
+
+impl Source {
+    /// 元画像 (未デコードならここでデコードする)
+    pub fn image(&self) -> Result<&DynamicImage, ErrorKind> {
+        if let Some(img) = self.img.get() {
+            return Ok(img);
+        }
+        let img = open_image(&self.path)?;
+        Ok(self.img.get_or_init(|| img))
+    }
+
+    /// 1 チャンネル 8 ビットを超える深度を持つか
+    pub fn is_high_depth(&self) -> bool {
+        self.bit_depth > 8
+    }
+}
+
+/// 変換済み (エンコード前) の出力 1 件分
+pub enum Frame<'a> {
+    // 静止画
+    Still(Cow<'a, DynamicImage>),
+    // アニメーション
+    Animated(Cow<'a, Animation>),
+    // 再エンコードせずに使う元ファイルのバイト列 (PNG → PNG、JPEG → JPEG XL)
+    Original(Vec<u8>),
+}
+
+/// 1 ファイルをパイプラインに通して出力する
+/// suffix はバリアント指定がない場合の出力ファイル名の接尾辞 ({stem}_{suffix}.{ext})
+pub fn run(path_str: &str, options: &ProcessOptions, suffix: &str) -> ProcessResult {
+    let path = PathBuf::from(path_str);
+    let locale = options.locale;
+    let failure = |original_size, kind| {
+        ProcessResult::failure(original_size, ProcessError::new(path_str, kind), locale)
+    };
+
+    if !path.exists() {
+        return failure(0, ErrorKind::NotFound);
+    }
+
+    let original_size = fs::metadata(&path).map(|m| m.len()).unwrap_or(0);
+    let stem = path
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or("output");
+
+    // 出力先ディレクトリを決定
+    let output_parent = if let Some(ref out_dir) = options.output_dir {
+        let out_path = PathBuf::from(out_dir);
+        // ディレクトリが存在しない場合は作成
+        if !out_path.exists() {
+            if let Err(e) = fs::create_dir_all(&out_path) {
+                return failure(
+                    original_size,
+                    ErrorKind::WriteFailed {
+                        output_path: out_dir.clone(),
+                        reason: e.to_string(),
+                    },
+                );
+            }
+        }
+        out_path
+    } else {
+        path.parent().unwrap_or(Path::new(".")).to_path_buf()
+    };
+
+    // 出力ターゲットを決定 (バリアント指定がなければ通常の設定から 1 件)
+    let use_variants = !options.variants.is_empty();
+    let targets = if use_variants {
+        options.variants.clone()
+    } else {
+        let resize = options.resize_enabled;
+        vec![OutputVariant {
+            width: options.width.filter(|_| resize),
+            height: options.height.filter(|_| resize),
+            format: options.output_format.clone(),
+            quality: options.quality,
+        }]
+    };
+
+    // ステージ 1: デコード (バリアントが複数あってもデコードは 1 回のみ)
+    let mut decode_steps = StepLog::default();
+    let source = match decode(&path, options) {
+        Ok(source) => source,
+        Err(e) => return failure(original_size, e),
+    };
+    if source.dropped_frames > 0 {
+        decode_steps.push(Step::DropDuplicates {
+            frames: source.dropped_frames,
+        });
+    }
+
+    // 再エンコードする出力があればピクセルのデコードもこのステージで行う
+    let needs_pixels = options.placeholder.is_some()
+        || targets
+            .iter()
+            .any(|target| !reuses_original(&source, target, options));
+    if needs_pixels {
+        if let Err(e) = source.image() {
+            return failure(original_size, e);
+        }
+    }
+
+    // プレースホルダー生成 (リサイズ前の元画像から)
+    let placeholder = match options.placeholder {
+        Some(ref placeholder_options) => {
+            let generated = source.image().and_then(|img| {
+                placeholder::generate(img, placeholder_options)
+                    .map_err(|reason| ErrorKind::TransformFailed { reason })
+            });
+            match generated {
+                Ok(p) => Some(p),
+                Err(e) => return failure(original_size, e),
+            }
+        }
+        None => None,
+    };
+    decode_steps.finish_stage(PipelineStage::Decode, None, (source.width, source.height));
+    let source_bit_depth = source.bit_depth;
+
+    let mut steps = mem::take(&mut decode_steps.stages);
+    let mut outputs: Vec<OutputEntry> = Vec::new();
+    let mut summaries: Vec<String> = Vec::new();
+    let mut quality_fallback = None;
+
+    for target in &targets {
+        let mut process_steps = StepLog::default();
+
+        // ステージ 2 〜 4: 変換 → エンコード → 最適化
+        let encoded = transform(&source, target, options, &mut process_steps).and_then(
+            |(frame, width, height)| {
+                let dimensions = (width, height);
+                process_steps.finish_stage(PipelineStage::Transform, None, dimensions);
+                let animated = matches!(frame, Frame::Animated(_));
+                let data = encode(frame, target, options, &mut process_steps)?;
+                let size = Some(data.len() as u64);
+                process_steps.finish_stage(PipelineStage::Encode, size, dimensions);
+                let data = optimize(data, target, animated, options, &mut process_steps)?;
+                let size = Some(data.len() as u64);
+                process_steps.finish_stage(PipelineStage::Optimize, size, dimensions);
+                Ok((data, width, height))
+            },
+        );
+        // 途中のバリアントで失敗した場合は書き込み済みの出力も削除し、ファイル単位で失敗として扱う
+        let (final_data, width, height) = match encoded {
+            Ok(encoded) => encoded,
+            Err(e @ ErrorKind::Skipped { .. }) => {
+                remove_outputs(&outputs);
+                return ProcessResult {
+                    warnings: source
+                        .warnings
+                        .into_iter()
+                        .map(|kind| kind.render(locale))
+                        .collect(),
+                    source_bit_depth,
+                    quality_fallback: Some(QualityFallback::Skip),
+                    ..failure(original_size, e)
+                };
+            }
+            Err(e) => {
+                remove_outputs(&outputs);
+                return failure(original_size, e);
+            }
+        };
+        quality_fallback = quality_fallback.or(process_steps.quality_fallback);
+
+        // ステージ 5: 書き込み (バリアントは幅で区別し、同名になる場合は高さ・クオリティも付ける)
+        let output_path = if use_variants {
+            variant_path(&output_parent, stem, target, width, height, &outputs)
+        } else {
+            let extension = target.format.extension();
+            output_parent.join(format!("{}_{}.{}", stem, suffix, extension))
+        };
+
+        if let Err(e) = fs::write(&output_path, &final_data) {
+            let _ = fs::remove_file(&output_path);
+            remove_outputs(&outputs);
+            return failure(
+                original_size,
+                ErrorKind::WriteFailed {
+                    output_path: output_path.to_string_lossy().to_string(),
+                    reason: e.to_string(),
+                },
+            );
+        }
+
+        process_steps.finish_stage(
+            PipelineStage::Write,
+            Some(final_data.len() as u64),
+            (width, height),
+        );
+        let output = Some(outputs.len());
+        steps.extend(
+            process_steps
+                .stages
+                .drain(..)
+                .map(|stage| StepResult { output, ..stage }),
+        );
+
+        summaries.push(process_steps.render(locale));
+        outputs.push(OutputEntry {
+            output_path: output_path.to_string_lossy().to_string(),
+            width,
+            height,
+            format: target.format.clone(),
+            size: final_data.len() as u64,
+        });
+    }
+
+    let result_size: u64 = outputs.iter().map(|o| o.size).sum();
+
+    ProcessResult {
+        success: true,
+        original_size,
+        result_size,
+        output_path: outputs
+            .first()
+            .map(|o| o.output_path.clone())
+            .unwrap_or_default(),
+        message: i18n::summary(
+            locale,
+            &summary(&decode_steps, &summaries, locale),
+            original_size,
+            result_size,
+        ),
+        outputs,
+        placeholder,
+        warnings: source
+            .warnings
+            .into_iter()
+            .map(|kind| kind.render(locale))
+            .collect(),
+        source_bit_depth,
+        quality_fallback,
+        error: None,
+        steps,
+    }
+}
+
+/// 書き込み済みの出力を削除する
+fn remove_outputs(outputs: &[OutputEntry]) {
+    for output in outputs {
+        let _ = fs::remove_file(&output.output_path);
+    }
+}
+
+/// バリアントの出力パス ({stem}_{幅}w.{ext})
+/// 幅とフォーマットが同じ出力が既にある場合は、寸法・クオリティ・連番の順に付けて区別する
+fn variant_path(
+    output_parent: &Path,
+    stem: &str,
+    target: &OutputVariant,
+    width: u32,
+    height: u32,
+    outputs: &[OutputEntry],
+) -> PathBuf {
+    let extension = target.format.extension();
+    let taken = |path: &Path| outputs.iter().any(|o| Path::new(&o.output_path) == path);
+    let candidates = [
+        format!("{}_{}w", stem, width),
+        format!("{}_{}x{}", stem, width, height),
+        format!("{}_{}x{}_q{}", stem, width, height, target.quality),
+    ];
+    candidates
+        .iter()
+        .map(|name| output_parent.join(format!("{}.{}", name, extension)))
+        .chain((2..).map(|n| {
+            output_parent.join(format!(
+                "{}_{}x{}_q{}_{}.{}",
+                stem, width, height, target.quality, n, extension
+            ))
+        }))
+        .find(|path| !taken(path))
+        .expect("連番は無限に続くため必ず見つかる")
+}
+
+// ============================================================================
+// ステージ 1: デコード
+// ============================================================================
+
+/// 入力ファイルをデコードする
+pub fn decode(path: &Path, options: &ProcessOptions) -> Result<Source, ErrorKind> {
+    let decode_failed = |reason| ErrorKind::DecodeFailed { reason };
+    let format = detect_format(path);
+    let mut warnings: Vec<WarningKind> = extension_warning(path, format).into_iter().collect();
+
+    // アニメーション画像は全フレームをデコードする (無効の場合は先頭フレームのみになることを警告する)
+    let mut dropped_frames = 0;
+    let animation = match options.animation {
+        Some(ref animation_options) => {
+            match animation::decode(path, format).map_err(decode_failed)? {
+                Some(mut anim) => {
+                    if animation_options.drop_duplicates {
+                        dropped_frames = animation::drop_duplicate_frames(&mut anim);
+                    }
+                    Some(anim)
+                }
+                None => None,
+            }
+        }
+        None => {
+            let frames = format
+                .and_then(|format| probe::count_frames(path, format).ok())
+                .unwrap_or(1);
+            if frames > 1 {
+                warnings.push(WarningKind::FirstFrameOnly { frames });
+            }
+            None
+        }
+    };
+
+    // SVG はパース結果を保持し、出力ごとに目標サイズでラスタライズする
+    let svg_tree = if svg::is_svg(path) {
+        Some(svg::load(path).map_err(decode_failed)?)
+    } else {
+        None
+    };
+
+    // アニメーションの場合は先頭フレーム、SVG は本来の寸法で描画したものを元画像として扱う
+    // 静止画はヘッダーのみ読み、ピクセルは必要になった時点でデコードする
+    let img = match (&animation, &svg_tree) {
+        (Some(anim), _) => Some(anim.first_frame()),
+        (None, Some(tree)) => Some(svg::render_intrinsic(tree).map_err(decode_failed)?),
+        (None, None) => None,
+    };
+    let (width, height, bit_depth) = match img {
+        Some(ref img) => (img.width(), img.height(), depth::bit_depth(img)),
+        None => read_header(path)?,
+    };
+
+    Ok(Source {
+        path: path.to_path_buf(),
+        format,
+        warnings,
+        animation,
+        dropped_frames,
+        svg_tree,
+        width,
+        height,
+        bit_depth,
+        img: img.map(OnceCell::from).unwrap_or_default(),
+    })
+}
+
+// ============================================================================
+// ステージ 2: 変換 (リサイズ・ビット深度変換)
+// ============================================================================
+
+/// 出力 1 件分の変換を行う (リサイズは常に元画像から行う)
+/// 変換後の画像と寸法を返す
+pub fn transform<'a>(
+    source: &'a Source,
+    target: &OutputVariant,
+    options: &ProcessOptions,
+    process_steps: &mut StepLog,
+) -> Result<(Frame<'a>, u32, u32), ErrorKind> {
+    let transform_failed = |reason| ErrorKind::TransformFailed { reason };
+
+    let (orig_w, orig_h) = (source.width, source.height);
+    let resize = target.width.is_some() || target.height.is_some();
+    let (width, height) = if resize {
+        let (new_width, new_height) = calculate_new_dimensions(
+            orig_w,
+            orig_h,
+            target.width,
+            target.height,
+            options.maintain_aspect_ratio,
+        );
+        if source.svg_tree.is_some() {
+            process_steps.push(Step::Rasterize {
+                width: new_width,
+                height: new_height,
+            });
+        } else {
+            process_steps.push(Step::Resize {
+                from: (orig_w, orig_h),
+                to: (new_width, new_height),
+            });
+        }
+        (new_width, new_height)
+    } else {
+        (orig_w, orig_h)
+    };
+
+    // JPEG → JPEG XL は係数ごと変換し、PNG → PNG と JPEG → JPEG は元のバイト列をそのまま最適化する
+    // (APNG は静止画として扱うため除外)
+    if reuses_original(source, target, options) {
+        let data = fs::read(&source.path).map_err(|e| ErrorKind::DecodeFailed {
+            reason: e.to_string(),
+        })?;
+        if target.format != OutputFormat::Png || !png_optimize::is_apng(&data) {
+            return Ok((Frame::Original(data), width, height));
+        }
+    }
+
+    if let Some(ref anim) = source.animation {
+        let resized = if resize {
+            Cow::Owned(animation::resize(anim, width, height))
+        } else {
+            Cow::Borrowed(anim)
+        };
+        if options.stages.is_empty() {
+            return Ok((Frame::Animated(resized), width, height));
+        }
+
+        // 追加ステージは全フレームに適用する
+        let mut anim = resized.into_owned();
+        let mut descriptions = Vec::new();
+        for frame in &mut anim.frames {
+            let img = DynamicImage::ImageRgba8(mem::take(&mut frame.image));
+            let (img, frame_descriptions) =
+                stage::apply_all(img, &options.stages).map_err(transform_failed)?;
+            frame.image = img.to_rgba8();
+            descriptions = frame_descriptions;
+        }
+        push_stages(process_steps, &options.stages, descriptions);
+        (anim.width, anim.height) = anim.frames[0].image.dimensions();
+        let (width, height) = (anim.width, anim.height);
+        return Ok((Frame::Animated(Cow::Owned(anim)), width, height));
+    }
+
+    // SVG はラスタライズ後の拡大縮小ではなく目標サイズで直接描画する
+    let resized = match source.svg_tree {
+        Some(ref tree) if resize => {
+            Cow::Owned(svg::render(tree, width, height).map_err(transform_failed)?)
+        }
+        _ if resize => Cow::Owned(source.image()?.resize_exact(
+            width,
+            height,
+            FilterType::Lanczos3,
+        )),
+        _ => Cow::Borrowed(source.image()?),
+    };
+
+    // 高ビット深度の入力は JPEG XL と 16 ビット保持の PNG 以外ではディザリングで 8 ビットにする
+    let keep_high_depth = match target.format {
+        OutputFormat::Jxl => true,
+        OutputFormat::Png => options.keep_16bit && !options.quantize_enabled,
+        _ => false,
+    };
+    let resized = if source.is_high_depth() && !keep_high_depth {
+        process_steps.push(Step::Dither {
+            bit_depth: source.bit_depth,
+        });
+        Cow::Owned(depth::dither_to_8bit(&resized))
+    } else {
+        resized
+    };
+
+    // 追加ステージ (回転などで寸法が変わる場合があるため、適用後の寸法を返す)
+    if options.stages.is_empty() {
+        return Ok((Frame::Still(resized), width, height));
+    }
+    let (img, descriptions) =
+        stage::apply_all(resized.into_owned(), &options.stages).map_err(transform_failed)?;
+    push_stages(process_steps, &options.stages, descriptions);
+    let (width, height) = img.dimensions();
+    Ok((Frame::Still(Cow::Owned(img)), width, height))
+}
+
+/// ピクセルにデコードせず元ファイルのバイト列を使う出力か (JPEG → JPEG XL、PNG → PNG、JPEG → JPEG)
+fn reuses_original(source: &Source, target: &OutputVariant, options: &ProcessOptions) -> bool {
+    if source.animation.is_some() || !options.stages.is_empty() {
+        return false;
+    }
+    let resize = target.width.is_some() || target.height.is_some();
+    match (&target.format, source.format) {
+        (OutputFormat::Jxl, Some(ImageFormat::Jpeg)) => {
+            let unchanged = calculate_new_dimensions(
+                source.width,
+                source.height,
+                target.width,
+                target.height,
+                options.maintain_aspect_ratio,
+            ) == (source.width, source.height);
+            options.jxl.lossless_jpeg && (!resize || unchanged)
+        }
+        (OutputFormat::Png, Some(ImageFormat::Png)) => {
+            !options.quantize_enabled && !resize && (!source.is_high_depth() || options.keep_16bit)
+        }
+        (OutputFormat::Jpeg, Some(ImageFormat::Jpeg)) => !resize,
+        _ => false,
+    }
+}
+
+/// 結果メッセージ用の処理内容 (デコード時のステップは先頭に 1 回だけ付ける)
+fn summary(decode_steps: &StepLog, summaries: &[String], locale: Locale) -> String {
+    let summary = summaries.join(" / ");
+    if decode_steps.steps.is_empty() {
+        summary
+    } else {
+        format!("{} → {}", decode_steps.render(locale), summary)
+    }
+}
+
+/// 追加ステージの説明を処理ステップとして記録する
+fn push_stages(process_steps: &mut StepLog, specs: &[StageSpec], descriptions: Vec<String>) {
+    for (spec, description) in specs.iter().zip(descriptions) {
+        process_steps.push(Step::Stage {
+            name: spec.name.clone(),
+            params: spec.params.clone(),
+            description,
+        });
+    }
+}
+
+// ============================================================================
+// ステージ 3: エンコード
+// ============================================================================
+
+/// 変換済みの出力をターゲットのフォーマットでエンコードする
+pub fn encode(
+    frame: Frame<'_>,
+    target: &OutputVariant,
+    options: &ProcessOptions,
+    process_steps: &mut StepLog,
+) -> Result<Vec<u8>, ErrorKind> {
+    match frame {
+        Frame::Original(data) if target.format == OutputFormat::Jxl => {
+            process_steps.push(Step::JpegTranscode);
+            jxl::transcode_jpeg(&data, &options.jxl).map_err(encode_failed(&target.format))
+        }
+        Frame::Original(data) => {
+            process_steps.push(Step::Passthrough);
+            Ok(data)
+        }
+        Frame::Animated(anim) => encode_animation(
+            &anim,
+            &target.format,
+            target.quality,
+            options,
+            process_steps,
+        ),
+        Frame::Still(img) => {
+            encode_image(&img, &target.format, target.quality, options, process_steps)
+        }
+    }
+}
+
+/// pngquant で減色する
+/// 最低クオリティを満たせない場合は fallback に従い、減色しない場合は None を返す
+fn quantize_with_fallback(
+    rgba_img: &RgbaImage,
+    quality: u8,
+    options: &QuantizeOptions,
+    fallback: QualityFallback,
+    process_steps: &mut StepLog,
+) -> Result<Option<quantize::Quantized>, ErrorKind> {
+    let quantize_failed = |e: QuantizeError| match e {
+        QuantizeError::QualityTooLow => ErrorKind::QualityTooLow {
+            min_quality: options.min_quality(quality),
+            quality,
+        },
+        QuantizeError::Failed(reason) => ErrorKind::QuantizeFailed { reason },
+    };
+    match quantize::quantize(rgba_img, quality, options) {
+        Ok(quantized) => {
+            process_steps.push(Step::Quantize {
+                quality,
+                options: options.clone(),
+            });
+            return Ok(Some(quantized));
+        }
+        Err(QuantizeError::QualityTooLow) if fallback != QualityFallback::Fail => {
+            process_steps.quality_fallback = Some(fallback);
+        }
+        Err(e) => return Err(quantize_failed(e)),
+    }
+
+    match fallback {
+        QualityFallback::Truecolor => {
+            process_steps.push(Step::TruecolorFallback);
+            Ok(None)
+        }
+        QualityFallback::LowerQuality => {
+            let relaxed = QuantizeOptions {
+                min_quality: Some(0),
+                palette_below_min_quality: false,
+                ..options.clone()
+            };
+            let quantized =
+                quantize::quantize(rgba_img, quality, &relaxed).map_err(quantize_failed)?;
+            process_steps.push(Step::QuantizeRetry {
+                quality,
+                options: relaxed,
+            });
+            Ok(Some(quantized))
+        }
+        _ => Err(ErrorKind::Skipped {
+            min_quality: options.min_quality(quality),
+            quality,
+        }),
+    }
+}
+
+/// PNG としてエンコードする (pngquant 有効時は減色、無効時はロスレス)
+pub fn encode_png(
+    img: &DynamicImage,
+    quality: u8,
+    quantize: Option<(&QuantizeOptions, QualityFallback)>,
+    process_steps: &mut StepLog,
+) -> Result<Vec<u8>, ErrorKind> {
+    let quantized = match quantize {
+        Some((quantize_options, fallback)) => {
+            let rgba_img = depth::to_rgba8(img);
+            quantize_with_fallback(
+                &rgba_img,
+                quality,
+                quantize_options,
+                fallback,
+                process_steps,
+            )?
+            .map(|quantized| (quantized, rgba_img.dimensions()))
+        }
+        None => None,
+    };
+
+    if let Some(((palette, indexed_pixels), (width, height))) = quantized {
+        let mut encoder = lodepng::Encoder::new();
+        for color in &palette {
+            let _ = encoder.info_raw_mut().palette_add(lodepng::RGBA {
+                r: color.r,
+                g: color.g,
+                b: color.b,
+                a: color.a,
+            });
+            let _ = encoder.info_png_mut().color.palette_add(lodepng::RGBA {
+                r: color.r,
+                g: color.g,
+                b: color.b,
+                a: color.a,
+            });
+        }
+
+        encoder.info_raw_mut().colortype = lodepng::ColorType::PALETTE;
+        encoder.info_raw_mut().set_bitdepth(8);
+        encoder.info_png_mut().color.colortype = lodepng::ColorType::PALETTE;
+        encoder.info_png_mut().color.set_bitdepth(8);
+
+        encoder
+            .encode(&indexed_pixels, width as usize, height as usize)
+            .map_err(|e| encode_failed(&OutputFormat::Png)(format!("{:?}", e)))
+    } else if depth::is_high_depth(img) {
+        // 高ビット深度はそのまま PNG に変換 (16 ビットはそのまま、浮動小数点は 16 ビットへ)
+        let mut cursor = Cursor::new(Vec::new());
+        depth::to_png16(img)
+            .write_to(&mut cursor, ImageFormat::Png)
+            .map_err(|e| encode_failed(&OutputFormat::Png)(e.to_string()))?;
+        Ok(cursor.into_inner())
+    } else {
+        // pngquant をスキップする場合はロスレスでパレット・グレースケール・ビット深度を削減する
+        png_optimize::encode_reduced(&img.to_rgba8()).map_err(encode_failed(&OutputFormat::Png))
+    }
+}
+
+/// エンコードエラーに変換する
+fn encode_failed(format: &OutputFormat) -> impl FnOnce(String) -> ErrorKind + '_ {
+    move |reason| ErrorKind::EncodeFailed {
+        format: format.clone(),
+        reason,
+    }
+}
+
+/// デコード済み画像を指定フォーマットでエンコードする
+fn encode_image(
+    img: &DynamicImage,
+    format: &OutputFormat,
+    quality: u8,
+    options: &ProcessOptions,
+    process_steps: &mut StepLog,
+) -> Result<Vec<u8>, ErrorKind> {
+    // 出力フォーマットに応じて処理を分岐
+    let data = match format {
+        OutputFormat::Png => {
+            // PNG 出力: pngquant (oxipng は最適化ステージで行う)
+            encode_png(
+                img,
+                quality,
+                options
+                    .quantize_enabled
+                    .then_some((&options.quantize, options.quality_fallback)),
+                process_steps,
+            )?
+        }
+        OutputFormat::Webp => {
+            // WebP 出力 (ロスレスは webp.lossless 指定時のみ)
+            process_steps.push(Step::Webp {
+                quality,
+                options: options.webp.clone(),
+            });
+            webp_output::encode(img, quality, &options.webp).map_err(encode_failed(format))?
+        }
+        OutputFormat::Gif => {
+            // GIF 出力: imagequant で最大 256 色 + 1 ビット透過
+            process_steps.push(Step::Gif {
+                quality,
+                options: options.quantize.clone(),
+            });
+            gif_output::encode(img, quality, &options.quantize).map_err(encode_failed(format))?
+        }
+        OutputFormat::Jxl => {
+            // JPEG XL 出力 (クオリティ 100 または lossless 指定でロスレス)
+            let jxl_options = &options.jxl;
+            process_steps.push(Step::Jxl {
+                lossless: jxl_options.is_lossless(quality),
+                distance: jxl_options.distance(quality),
+                effort: jxl_options.effort,
+            });
+            jxl::encode(img, quality, jxl_options).map_err(encode_failed(format))?
+        }
+        OutputFormat::Jpeg => {
+            // JPEG 出力 (ハフマン符号の最適化は最適化ステージで行う)
+            process_steps.push(Step::Jpeg { quality });
+            jpeg_output::encode(img, quality).map_err(encode_failed(format))?
+        }
+    };
+
+    Ok(data)
+}
+
+/// アニメーションをフレームを保ったままエンコードする
+/// PNG 出力は APNG、WebP / GIF 出力はそれぞれのアニメーション形式になる
+fn encode_animation(
+    anim: &Animation,
+    format: &OutputFormat,
+    quality: u8,
+    options: &ProcessOptions,
+    process_steps: &mut StepLog,
+) -> Result<Vec<u8>, ErrorKind> {
+    let frames = anim.frames.len();
+
+    let encoded = match format {
+        OutputFormat::Png => {
+            let shared_palette = options.animation.as_ref().is_some_and(|a| a.shared_palette);
+            if shared_palette {
+                process_steps.push(Step::Apng {
+                    frames,
+                    shared_palette: Some((quality, options.quantize.clone())),
+                });
+                animation::encode_apng(anim, Some((quality, &options.quantize)))
+            } else {
+                process_steps.push(Step::Apng {
+                    frames,
+                    shared_palette: None,
+                });
+                animation::encode_apng(anim, None)
+            }
+        }
+        OutputFormat::Webp => {
+            process_steps.push(Step::AnimatedWebp {
+                frames,
+                quality,
+                options: options.webp.clone(),
+            });
+            animation::encode_webp(anim, quality, &options.webp)
+        }
+        OutputFormat::Gif => {
+            process_steps.push(Step::AnimatedGif {
+                frames,
+                quality,
+                options: options.quantize.clone(),
+            });
+            gif_output::encode_animation(anim, quality, &options.quantize)
+        }
+        OutputFormat::Jxl | OutputFormat::Jpeg => {
+            // アニメーション JPEG XL・JPEG には未対応のため先頭フレームのみ
+            process_steps.push(Step::FirstFrameOnly { frames });
+            return encode_image(&anim.first_frame(), format, quality, options, process_steps);
+        }
+    };
+    encoded.map_err(encode_failed(format))
+}
+
+// ============================================================================
+// ステージ 4: 最適化
+// ============================================================================
+
+/// エンコード済みのデータを最適化する (静止画 PNG の oxipng と JPEG のハフマン符号の最適化)
+pub fn optimize(
+    data: Vec<u8>,
+    target: &OutputVariant,
+    animated: bool,
+    options: &ProcessOptions,
+    process_steps: &mut StepLog,
+) -> Result<Vec<u8>, ErrorKind> {
+    match target.format {
+        OutputFormat::Png if !animated => optimize_png(
+            data,
+            options.optimize_enabled.then_some(&options.oxipng),
+            process_steps,
+        ),
+        OutputFormat::Jpeg if options.optimize_enabled => {
+            let optimized = jpeg_optimize::optimize(&data, &options.jpeg)
+                .map_err(|reason| ErrorKind::OptimizeFailed { reason })?;
+            process_steps.push(Step::JpegOptimize {
+                options: options.jpeg.clone(),
+            });
+            Ok(optimized)
+        }
+        _ => Ok(data),
+    }
+}
+
+/// oxipng で PNG を最適化する
+/// pngquant がクオリティ不足でフルカラーに切り替えた場合は無効でも既定の設定で行う
+pub fn optimize_png(
+    data: Vec<u8>,
+    optimize: Option<&OxipngOptions>,
+    process_steps: &mut StepLog,
+) -> Result<Vec<u8>, ErrorKind> {
+    let default_oxipng;
+    let oxipng_options = match optimize {
+        Some(oxipng_options) => oxipng_options,
+        None if process_steps.quality_fallback == Some(QualityFallback::Truecolor) => {
+            default_oxipng = OxipngOptions::default();
+            &default_oxipng
+        }
+        None => return Ok(data),
+    };
+
+    match oxipng::optimize_from_memory(&data, &oxipng_options.to_oxipng()) {
+        Ok(optimized) => {
+            process_steps.push(Step::Oxipng {
+                options: oxipng_options.clone(),
+            });
+            Ok(optimized)
+        }
+        Err(e) => Err(ErrorKind::OptimizeFailed {
+            reason: format!("oxipng: {}", e),
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use image::{ImageBuffer, Rgb, Rgba};
+
+    use super::*;
+
+    /// テスト用の一時ディレクトリ (テストごとに分ける)
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("image-optimizer-{}-{}", name, std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    /// 各フォーマットの小さな画像をメモリ上で作りファイルに書き出す
+    fn write_fixtures(dir: &Path) -> Vec<(PathBuf, u8)> {
+        let rgb16 = DynamicImage::ImageRgb16(ImageBuffer::from_fn(16, 8, |x, y| {
+            Rgb([x as u16 * 4096, y as u16 * 8192, 0x1234])
+        }));
+        let rgba16 = DynamicImage::ImageRgba16(ImageBuffer::from_fn(16, 8, |x, y| {
+            Rgba([x as u16 * 4096, y as u16 * 8192, 0x1234, u16::MAX])
+        }));
+        let rgb8 = DynamicImage::ImageRgb8(ImageBuffer::from_fn(16, 8, |x, y| {
+            Rgb([x as u8 * 16, y as u8 * 32, 128])
+        }));
+        let rgba8 = DynamicImage::ImageRgba8(ImageBuffer::from_fn(16, 8, |x, y| {
+            Rgba([x as u8 * 16, y as u8 * 32, 128, 200])
+        }));
+
+        [
+            ("tiff16.tiff", rgb16, ImageFormat::Tiff, 16),
+            ("png16.png", rgba16, ImageFormat::Png, 16),
+            ("bmp.bmp", rgb8, ImageFormat::Bmp, 8),
+            ("qoi.qoi", rgba8, ImageFormat::Qoi, 8),
+        ]
+        .into_iter()
+        .map(|(name, img, format, bit_depth)| {
+            let mut data = Cursor::new(Vec::new());
+            img.write_to(&mut data, format).unwrap();
+            let path = dir.join(name);
+            fs::write(&path, data.into_inner()).unwrap();
+            (path, bit_depth)
+        })
+        .collect()
+    }
+
+    /// PNG の IHDR に書かれたビット深度
+    fn png_bit_depth(data: &[u8]) -> u8 {
+        assert_eq!(&data[12..16], b"IHDR");
+        data[24]
+    }
+
+    #[test]
+    fn decodes_high_depth_and_legacy_formats() {
+        let dir = temp_dir("decode");
+        for (path, bit_depth) in write_fixtures(&dir) {
+            let img = open_image(&path).unwrap();
+            assert_eq!(img.dimensions(), (16, 8), "{:?}", path);
+            assert_eq!(depth::bit_depth(&img), bit_depth, "{:?}", path);
+
+            let source = decode(&path, &ProcessOptions::default()).unwrap();
+            assert_eq!((source.width, source.height), (16, 8), "{:?}", path);
+            assert_eq!(source.bit_depth, bit_depth, "{:?}", path);
+            assert_eq!(source.image().unwrap().dimensions(), (16, 8));
+        }
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn keep_16bit_writes_16bit_png() {
+        let dir = temp_dir("keep16");
+        let fixtures = write_fixtures(&dir);
+        let out_dir = dir.join("out");
+        fs::create_dir_all(&out_dir).unwrap();
+
+        for keep_16bit in [true, false] {
+            let options = ProcessOptions {
+                output_dir: Some(out_dir.to_string_lossy().to_string()),
+                keep_16bit,
+                ..Default::default()
+            };
+            // TIFF は再エンコード、PNG は元のバイト列をそのまま使う経路
+            for (path, _) in fixtures.iter().filter(|(_, depth)| *depth == 16) {
+                let result = run(&path.to_string_lossy(), &options, "out");
+                assert!(result.success, "{}", result.message);
+                assert_eq!(result.source_bit_depth, 16);
+                let stem = path.file_stem().unwrap().to_string_lossy();
+                assert!(
+                    result.output_path.ends_with(&format!("{}_out.png", stem)),
+                    "{}",
+                    result.output_path
+                );
+
+                let data = fs::read(&result.output_path).unwrap();
+                let expected = if keep_16bit { 16 } else { 8 };
+                assert_eq!(png_bit_depth(&data), expected, "{:?}", path);
+            }
+        }
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn failed_variant_removes_written_outputs() {
+        let dir = temp_dir("partial");
+        let path = dir.join("gradient.png");
+        ImageBuffer::from_fn(64, 64, |x, y| Rgb([x as u8 * 4, y as u8 * 4, 128u8]))
+            .save(&path)
+            .unwrap();
+
+        // 1 件目 (WebP) は書き込まれるが、2 件目 (PNG) は 2 色で最低クオリティ 100 を満たせずスキップ
+        let variant = |format| OutputVariant {
+            width: Some(32),
+            height: None,
+            format,
+            quality: 100,
+        };
+        let options = ProcessOptions {
+            variants: vec![variant(OutputFormat::Webp), variant(OutputFormat::Png)],
+            quantize_enabled: true,
+            quantize: QuantizeOptions {
+                max_colors: 2,
+                min_quality: Some(100),
+                ..Default::default()
+            },
+            quality_fallback: QualityFallback::Skip,
+            ..Default::default()
+        };
+        let result = run(&path.to_string_lossy(), &options, "out");
+        assert!(!result.success);
+        assert!(result.outputs.is_empty());
+        assert!(!dir.join("gradient_32w.webp").exists());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn jpeg_output_reuses_jpeg_input_and_encodes_others() {
+        let dir = temp_dir("jpeg");
+        let jpeg = dir.join("photo.jpg");
+        ImageBuffer::from_fn(32, 32, |x, y| Rgb([x as u8 * 8, y as u8 * 8, 128u8]))
+            .save(&jpeg)
+            .unwrap();
+        let png = dir.join("logo.png");
+        ImageBuffer::from_fn(32, 32, |x, y| {
+            Rgba([x as u8 * 8, y as u8 * 8, 128u8, 100u8])
+        })
+        .save(&png)
+        .unwrap();
+
+        let out_dir = dir.join("out");
+        let options = ProcessOptions {
+            output_format: OutputFormat::Jpeg,
+            optimize_enabled: true,
+            output_dir: Some(out_dir.to_string_lossy().to_string()),
+            ..Default::default()
+        };
+        for (path, reencoded) in [(jpeg, false), (png, true)] {
+            let result = run(&path.to_string_lossy(), &options, "optimized");
+            assert!(result.success, "{:?}", result.error);
+
+            let output = PathBuf::from(&result.output_path);
+            assert_eq!(output.parent(), Some(out_dir.as_path()));
+            assert!(output.to_string_lossy().ends_with("_optimized.jpg"));
+            assert_eq!(detect_format(&output), Some(ImageFormat::Jpeg));
+
+            let operations: Vec<&Step> = result.steps.iter().flat_map(|s| &s.operations).collect();
+            assert_eq!(
+                operations.iter().any(|s| matches!(s, Step::Jpeg { .. })),
+                reencoded
+            );
+            assert!(operations
+                .iter()
+                .any(|s| matches!(s, Step::JpegOptimize { .. })));
+        }
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    fn entry(output_path: PathBuf) -> OutputEntry {
+        OutputEntry {
+            output_path: output_path.to_string_lossy().to_string(),
+            width: 0,
+            height: 0,
+            format: OutputFormat::Png,
+            size: 0,
+        }
+    }
+
+    #[test]
+    fn variant_paths_do_not_collide() {
+        let dir = Path::new("out");
+        let target = |quality| OutputVariant {
+            width: Some(100),
+            height: None,
+            format: OutputFormat::Webp,
+            quality,
+        };
+
+        let mut outputs = Vec::new();
+        let mut paths = Vec::new();
+        for (quality, height) in [(80, 50), (60, 50), (80, 60), (80, 50), (80, 50)] {
+            let path = variant_path(dir, "a", &target(quality), 100, height, &outputs);
+            outputs.push(entry(path.clone()));
+            paths.push(path);
+        }
+
+        assert_eq!(
+            paths,
+            [
+                dir.join("a_100w.webp"),
+                dir.join("a_100x50.webp"),
+                dir.join("a_100x60.webp"),
+                dir.join("a_100x50_q80.webp"),
+                dir.join("a_100x50_q80_2.webp"),
+            ]
+        );
+    }
+}
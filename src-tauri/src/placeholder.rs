@@ -0,0 +1,105 @@
+use base64::Engine;
+use image::imageops::FilterType;
+use image::DynamicImage;
+use serde::{Deserialize, Serialize};
+
+// ============================================================================
+// 低画質プレースホルダー (LQIP)
+// ============================================================================
+
+/// BlurHash の成分数 (横 × 縦)
+const BLURHASH_COMPONENTS: (u32, u32) = (4, 3);
+/// BlurHash 計算用の縮小サイズ (長辺)
+const BLURHASH_SAMPLE_SIZE: u32 = 32;
+/// ThumbHash の入力上限 (長辺 100px 以下である必要がある)
+const THUMBHASH_SAMPLE_SIZE: u32 = 100;
+/// インラインプレビューの長辺
+const PREVIEW_SIZE: u32 = 16;
+/// インラインプレビューの WebP クオリティ
+const PREVIEW_QUALITY: f32 = 40.0;
+
+/// ハッシュ形式
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum PlaceholderHash {
+    Blurhash,
+    Thumbhash,
+}
+
+/// プレースホルダー生成オプション
+#[derive(Debug, Clone, Deserialize)]
+pub struct PlaceholderOptions {
+    // BlurHash / ThumbHash (None の場合は生成しない)
+    #[serde(default)]
+    pub hash: Option<PlaceholderHash>,
+    // 極小 WebP の data URI を生成するか
+    #[serde(default)]
+    pub preview: bool,
+}
+
+/// 生成したプレースホルダー
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Placeholder {
+    pub blurhash: Option<String>,
+    // ThumbHash (base64)
+    pub thumbhash: Option<String>,
+    // data:image/webp;base64,...
+    pub data_uri: Option<String>,
+}
+
+/// デコード済み画像からプレースホルダーを生成
+pub fn generate(img: &DynamicImage, options: &PlaceholderOptions) -> Result<Placeholder, String> {
+    let mut placeholder = Placeholder::default();
+
+    match options.hash {
+        Some(PlaceholderHash::Blurhash) => {
+            let small = img
+                .resize(
+                    BLURHASH_SAMPLE_SIZE,
+                    BLURHASH_SAMPLE_SIZE,
+                    FilterType::Triangle,
+                )
+                .to_rgba8();
+            let (components_x, components_y) = BLURHASH_COMPONENTS;
+            let hash = blurhash::encode(
+                components_x,
+                components_y,
+                small.width(),
+                small.height(),
+                small.as_raw(),
+            )
+            .map_err(|e| format!("BlurHash: {}", e))?;
+            placeholder.blurhash = Some(hash);
+        }
+        Some(PlaceholderHash::Thumbhash) => {
+            let small = img
+                .resize(
+                    THUMBHASH_SAMPLE_SIZE,
+                    THUMBHASH_SAMPLE_SIZE,
+                    FilterType::Triangle,
+                )
+                .to_rgba8();
+            let hash = thumbhash::rgba_to_thumb_hash(
+                small.width() as usize,
+                small.height() as usize,
+                small.as_raw(),
+            );
+            placeholder.thumbhash = Some(base64::engine::general_purpose::STANDARD.encode(hash));
+        }
+        None => {}
+    }
+
+    if options.preview {
+        let small = img
+            .resize(PREVIEW_SIZE, PREVIEW_SIZE, FilterType::Triangle)
+            .to_rgba8();
+        let encoder = webp::Encoder::from_rgba(small.as_raw(), small.width(), small.height());
+        let webp_data = encoder.encode(PREVIEW_QUALITY);
+        placeholder.data_uri = Some(format!(
+            "data:image/webp;base64,{}",
+            base64::engine::general_purpose::STANDARD.encode(&*webp_data)
+        ));
+    }
+
+    Ok(placeholder)
+}
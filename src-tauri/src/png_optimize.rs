@@ -0,0 +1,169 @@
+use std::num::NonZeroU64;
+use std::time::Duration;
+
+use image::RgbaImage;
+use oxipng::{Deflater, StripChunks, ZopfliOptions};
+use serde::{Deserialize, Serialize};
+
+use crate::i18n::Locale;
+
+// ============================================================================
+// oxipng 設定
+// ============================================================================
+
+/// インターレースの扱い
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Interlacing {
+    // 元ファイルの設定を維持
+    Keep,
+    // インターレースなし
+    #[default]
+    Off,
+    // Adam7 インターレース
+    Adam7,
+}
+
+/// oxipng 最適化オプション
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OxipngOptions {
+    // プリセット (0 〜 6、大きいほど多くのフィルター・設定を試す)
+    #[serde(default = "default_preset")]
+    pub preset: u8,
+    // libdeflater の圧縮レベル (0 〜 12、Zopfli 使用時は無視)
+    #[serde(default = "default_compression")]
+    pub compression: u8,
+    // Zopfli の反復回数 (指定時は libdeflater の代わりに Zopfli を使う、非常に低速)
+    #[serde(default)]
+    pub zopfli_iterations: Option<u32>,
+    // インターレース
+    #[serde(default)]
+    pub interlace: Interlacing,
+    // 透明ピクセルの色を変更して圧縮率を上げる
+    #[serde(default = "crate::default_true")]
+    pub optimize_alpha: bool,
+    // フィルター選択を高速評価で絞り込む
+    #[serde(default = "crate::default_true")]
+    pub fast_evaluation: bool,
+    // 1 ファイルあたりの処理時間の上限 (ミリ秒、超過後は以降の試行を省略)
+    #[serde(default)]
+    pub timeout_ms: Option<u64>,
+}
+
+fn default_preset() -> u8 {
+    4
+}
+
+fn default_compression() -> u8 {
+    12
+}
+
+impl Default for OxipngOptions {
+    fn default() -> Self {
+        OxipngOptions {
+            preset: default_preset(),
+            compression: default_compression(),
+            zopfli_iterations: None,
+            interlace: Interlacing::default(),
+            optimize_alpha: true,
+            fast_evaluation: true,
+            timeout_ms: None,
+        }
+    }
+}
+
+impl OxipngOptions {
+    /// oxipng の Options に変換する
+    pub fn to_oxipng(&self) -> oxipng::Options {
+        let mut options = oxipng::Options::from_preset(self.preset.min(6));
+        options.deflater = match self
+            .zopfli_iterations
+            .and_then(|n| NonZeroU64::new(n as u64))
+        {
+            Some(iteration_count) => Deflater::Zopfli(ZopfliOptions {
+                iteration_count,
+                ..Default::default()
+            }),
+            None => Deflater::Libdeflater {
+                compression: self.compression.min(12),
+            },
+        };
+        options.interlace = match self.interlace {
+            Interlacing::Keep => None,
+            Interlacing::Off => Some(false),
+            Interlacing::Adam7 => Some(true),
+        };
+        options.strip = StripChunks::Safe;
+        options.optimize_alpha = self.optimize_alpha;
+        options.fast_evaluation = self.fast_evaluation;
+        options.timeout = self.timeout_ms.map(Duration::from_millis);
+        options
+    }
+
+    /// 処理ステップ表示用の説明
+    pub fn describe(&self, locale: Locale) -> String {
+        let mut parts = vec![format!(
+            "{} {}",
+            locale.pick("プリセット", "preset"),
+            self.preset.min(6)
+        )];
+        match self.zopfli_iterations.filter(|&n| n > 0) {
+            Some(n) => parts.push(match locale {
+                Locale::Ja => format!("Zopfli {} 回", n),
+                Locale::En => format!("Zopfli {} iterations", n),
+            }),
+            None => parts.push(format!(
+                "{} {}",
+                locale.pick("圧縮レベル", "compression level"),
+                self.compression.min(12)
+            )),
+        }
+        if self.interlace == Interlacing::Adam7 {
+            parts.push("Adam7".to_string());
+        }
+        parts.join(", ")
+    }
+}
+
+// ============================================================================
+// ロスレス PNG
+// ============================================================================
+
+/// PNG のシグネチャ
+const PNG_SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1A, b'\n'];
+
+/// アニメーション PNG (IDAT より前に acTL チャンクがある) か
+pub fn is_apng(data: &[u8]) -> bool {
+    if !data.starts_with(&PNG_SIGNATURE) {
+        return false;
+    }
+    let mut offset = PNG_SIGNATURE.len();
+    while offset + 8 <= data.len() {
+        let length = u32::from_be_bytes([
+            data[offset],
+            data[offset + 1],
+            data[offset + 2],
+            data[offset + 3],
+        ]) as usize;
+        match &data[offset + 4..offset + 8] {
+            b"acTL" => return true,
+            b"IDAT" | b"IEND" => return false,
+            _ => offset += 12 + length,
+        }
+    }
+    false
+}
+
+/// 8 ビット RGBA 画像をロスレスで PNG にする
+/// 色数・グレースケール・アルファの有無を調べ、パレット / グレースケール / 低ビット深度のうち最小の形式で書き出す
+pub fn encode_reduced(image: &RgbaImage) -> Result<Vec<u8>, String> {
+    let (width, height) = image.dimensions();
+    let mut encoder = lodepng::Encoder::new();
+    encoder.set_auto_convert(true);
+    encoder.info_raw_mut().colortype = lodepng::ColorType::RGBA;
+    encoder.info_raw_mut().set_bitdepth(8);
+
+    encoder
+        .encode(image.as_raw(), width as usize, height as usize)
+        .map_err(|e| format!("lodepng: {:?}", e))
+}
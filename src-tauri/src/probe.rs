@@ -0,0 +1,184 @@
+use std::fs::File;
+use std::io::{BufReader, Read, Seek, SeekFrom};
+use std::path::Path;
+
+use image::{ImageDecoder, ImageFormat};
+
+use crate::error::ErrorKind;
+use crate::{image_error, open_reader, svg};
+
+// ============================================================================
+// ヘッダーのみの画像情報取得 (ピクセルはデコードしない)
+// ============================================================================
+
+/// ヘッダーから読み取った画像情報
+#[derive(Debug, Clone)]
+pub struct ProbeInfo {
+    // フォーマット名 ("png", "jpeg", "svg" など)
+    pub format: String,
+    pub width: u32,
+    pub height: u32,
+    // "Rgba8" などのカラータイプ名
+    pub color_type: String,
+    // 1 チャンネルあたりのビット深度
+    pub bit_depth: u8,
+    // フレーム数 (静止画は 1)
+    pub frame_count: u32,
+}
+
+/// ファイル先頭を読んでフォーマット・寸法・カラータイプを取得する
+pub fn probe(path: &Path) -> Result<ProbeInfo, ErrorKind> {
+    // SVG はピクセルを持たないためパースして寸法のみ取得する
+    if svg::is_svg(path) {
+        let tree = svg::load(path).map_err(|reason| ErrorKind::DecodeFailed { reason })?;
+        let (width, height) = svg::intrinsic_size(&tree);
+        return Ok(ProbeInfo {
+            format: "svg".to_string(),
+            width,
+            height,
+            color_type: "Rgba8".to_string(),
+            bit_depth: 8,
+            frame_count: 1,
+        });
+    }
+
+    let reader = open_reader(path)?;
+    let format = reader
+        .format()
+        .ok_or_else(|| ErrorKind::UnsupportedFormat {
+            reason: "unknown image format".to_string(),
+        })?;
+
+    let decoder = reader.into_decoder().map_err(image_error)?;
+    let (width, height) = decoder.dimensions();
+    let color_type = decoder.original_color_type();
+    let bit_depth = (color_type.bits_per_pixel() / color_type.channel_count().max(1) as u16) as u8;
+
+    let frame_count = count_frames(path, format).unwrap_or(1);
+
+    Ok(ProbeInfo {
+        format: format_name(format),
+        width,
+        height,
+        color_type: format!("{:?}", color_type),
+        bit_depth,
+        frame_count,
+    })
+}
+
+/// フォーマット名 ("png", "jpeg" など)
+pub fn format_name(format: ImageFormat) -> String {
+    format!("{:?}", format).to_lowercase()
+}
+
+/// アニメーション対応フォーマットのフレーム数をチャンク/ブロック構造から数える
+pub fn count_frames(path: &Path, format: ImageFormat) -> std::io::Result<u32> {
+    let mut reader = BufReader::new(File::open(path)?);
+    match format {
+        ImageFormat::Png => count_png_frames(&mut reader),
+        ImageFormat::Gif => count_gif_frames(&mut reader),
+        ImageFormat::WebP => count_webp_frames(&mut reader),
+        _ => Ok(1),
+    }
+}
+
+/// APNG: IDAT より前にある acTL チャンクの num_frames
+fn count_png_frames<R: Read + Seek>(reader: &mut R) -> std::io::Result<u32> {
+    reader.seek(SeekFrom::Start(8))?;
+    loop {
+        let mut header = [0u8; 8];
+        reader.read_exact(&mut header)?;
+        let length = u32::from_be_bytes([header[0], header[1], header[2], header[3]]);
+        match &header[4..8] {
+            b"acTL" => {
+                let mut num_frames = [0u8; 4];
+                reader.read_exact(&mut num_frames)?;
+                return Ok(u32::from_be_bytes(num_frames).max(1));
+            }
+            b"IDAT" | b"IEND" => return Ok(1),
+            // データ + CRC を読み飛ばす
+            _ => {
+                reader.seek(SeekFrom::Current(length as i64 + 4))?;
+            }
+        }
+    }
+}
+
+/// GIF: 画像記述子 (0x2C) の数
+fn count_gif_frames<R: Read + Seek>(reader: &mut R) -> std::io::Result<u32> {
+    // ヘッダー (6) + 論理画面記述子 (7)
+    let mut header = [0u8; 13];
+    reader.read_exact(&mut header)?;
+    let flags = header[10];
+    if flags & 0x80 != 0 {
+        let table_size = 3 * (1u32 << ((flags & 0x07) + 1));
+        reader.seek(SeekFrom::Current(table_size as i64))?;
+    }
+
+    let mut frames = 0;
+    loop {
+        let mut introducer = [0u8; 1];
+        if reader.read_exact(&mut introducer).is_err() {
+            break;
+        }
+        match introducer[0] {
+            // 画像記述子
+            0x2C => {
+                frames += 1;
+                let mut descriptor = [0u8; 9];
+                reader.read_exact(&mut descriptor)?;
+                let flags = descriptor[8];
+                if flags & 0x80 != 0 {
+                    let table_size = 3 * (1u32 << ((flags & 0x07) + 1));
+                    reader.seek(SeekFrom::Current(table_size as i64))?;
+                }
+                // LZW 最小コードサイズ
+                reader.seek(SeekFrom::Current(1))?;
+                skip_gif_sub_blocks(reader)?;
+            }
+            // 拡張ブロック
+            0x21 => {
+                reader.seek(SeekFrom::Current(1))?;
+                skip_gif_sub_blocks(reader)?;
+            }
+            // トレーラー
+            _ => break,
+        }
+    }
+
+    Ok(frames.max(1))
+}
+
+/// GIF のデータサブブロック列を読み飛ばす
+fn skip_gif_sub_blocks<R: Read + Seek>(reader: &mut R) -> std::io::Result<()> {
+    loop {
+        let mut size = [0u8; 1];
+        reader.read_exact(&mut size)?;
+        if size[0] == 0 {
+            return Ok(());
+        }
+        reader.seek(SeekFrom::Current(size[0] as i64))?;
+    }
+}
+
+/// WebP: ANMF チャンクの数 (アニメーションでなければ 1)
+fn count_webp_frames<R: Read + Seek>(reader: &mut R) -> std::io::Result<u32> {
+    // "RIFF" + サイズ + "WEBP"
+    reader.seek(SeekFrom::Start(12))?;
+
+    let mut frames = 0;
+    loop {
+        let mut header = [0u8; 8];
+        if reader.read_exact(&mut header).is_err() {
+            break;
+        }
+        let length = u32::from_le_bytes([header[4], header[5], header[6], header[7]]);
+        if &header[0..4] == b"ANMF" {
+            frames += 1;
+        }
+        // チャンクは偶数バイトにパディングされる
+        reader.seek(SeekFrom::Current((length + (length & 1)) as i64))?;
+    }
+
+    Ok(frames.max(1))
+}
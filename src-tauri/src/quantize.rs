@@ -0,0 +1,381 @@
+use std::fmt;
+
+use image::RgbaImage;
+use imagequant::RGBA;
+use serde::{Deserialize, Serialize};
+
+use crate::i18n::Locale;
+
+// ============================================================================
+// imagequant による減色
+// ============================================================================
+
+/// 減色の詳細設定
+/// UI 素材はディザリングなし・少色数、写真はディザリングあり・256 色が向いている
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QuantizeOptions {
+    // ディザリングの強さ (0.0 〜 1.0)
+    #[serde(default = "default_dithering")]
+    pub dithering: f32,
+    // 速度 (1 〜 10、小さいほど遅く高品質)
+    #[serde(default = "default_speed")]
+    pub speed: i32,
+    // パレットの最大色数 (2 〜 256)
+    #[serde(default = "default_max_colors")]
+    pub max_colors: u32,
+    // 最低クオリティ (None の場合は最高クオリティ - 10)
+    #[serde(default)]
+    pub min_quality: Option<u8>,
+    // ポスタリゼーションのビット数 (0 〜 4、減色先が RGB565 などの場合に指定)
+    #[serde(default)]
+    pub posterization: u8,
+    // 固定パレットの指定 (カラーコードの一覧またはパレットファイル)
+    #[serde(default)]
+    pub fixed_palette: Option<PaletteSource>,
+    // 減色先のパレット (指定時はパレットを生成せず、この色にリマップする)
+    #[serde(skip)]
+    pub palette: Option<Vec<RGBA>>,
+    // 共通パレットが最低クオリティを満たせなかった (各画像は代替処理に従う)
+    #[serde(skip)]
+    pub palette_below_min_quality: bool,
+}
+
+/// 固定パレットの指定方法
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PaletteSource {
+    // 16 進カラーコードの一覧 (#rgb / #rrggbb / #rrggbbaa)
+    Colors(Vec<String>),
+    // パレットファイル (.gpl / .act / 参照用 PNG / 1 行 1 色のカラーコード一覧)
+    File(String),
+}
+
+fn default_dithering() -> f32 {
+    1.0
+}
+
+fn default_speed() -> i32 {
+    4
+}
+
+fn default_max_colors() -> u32 {
+    256
+}
+
+impl Default for QuantizeOptions {
+    fn default() -> Self {
+        QuantizeOptions {
+            dithering: default_dithering(),
+            speed: default_speed(),
+            max_colors: default_max_colors(),
+            min_quality: None,
+            posterization: 0,
+            fixed_palette: None,
+            palette: None,
+            palette_below_min_quality: false,
+        }
+    }
+}
+
+impl QuantizeOptions {
+    /// 最低クオリティ (最高クオリティを超えないように丸める)
+    pub fn min_quality(&self, quality: u8) -> u8 {
+        self.min_quality
+            .unwrap_or_else(|| quality.saturating_sub(10))
+            .min(quality)
+    }
+
+    /// 処理ステップ表示用の説明
+    pub fn describe(&self, quality: u8, locale: Locale) -> String {
+        let ja = locale == Locale::Ja;
+        if let Some(ref palette) = self.palette {
+            let colors = palette.len().min(256);
+            let mut description = if ja {
+                format!("パレット {} 色にリマップ", colors)
+            } else {
+                format!("remapped to {}-color palette", colors)
+            };
+            if self.dithering <= 0.0 {
+                description.push_str(locale.pick(", ディザリングなし", ", no dithering"));
+            }
+            return description;
+        }
+
+        let mut parts = vec![format!(
+            "{} {}-{}",
+            locale.pick("クオリティ", "quality"),
+            self.min_quality(quality),
+            quality
+        )];
+        if self.max_colors < 256 {
+            parts.push(format!(
+                "{} {}",
+                self.max_colors,
+                locale.pick("色", "colors")
+            ));
+        }
+        if self.dithering <= 0.0 {
+            parts.push(locale.pick("ディザリングなし", "no dithering").to_string());
+        } else if self.dithering < 1.0 {
+            parts.push(format!(
+                "{} {:.2}",
+                locale.pick("ディザリング", "dithering"),
+                self.dithering
+            ));
+        }
+        if self.posterization > 0 {
+            parts.push(if ja {
+                format!("ポスタリゼーション {} ビット", self.posterization)
+            } else {
+                format!("posterization {} bits", self.posterization)
+            });
+        }
+        parts.join(", ")
+    }
+}
+
+/// 最低クオリティを満たせなかったときの対応
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum QualityFallback {
+    // エラーとして扱う
+    #[default]
+    Fail,
+    // 減色せずにフルカラーのロスレス PNG (+ oxipng) で出力
+    Truecolor,
+    // 最低クオリティを 0 にして再試行
+    LowerQuality,
+    // そのファイルを出力せずにスキップ
+    Skip,
+}
+
+/// 減色結果 (パレットとインデックス列)
+pub type Quantized = (Vec<RGBA>, Vec<u8>);
+
+/// 減色の失敗理由
+#[derive(Debug)]
+pub enum QuantizeError {
+    // 最低クオリティを満たすパレットを作れなかった
+    QualityTooLow,
+    Failed(String),
+}
+
+impl From<String> for QuantizeError {
+    fn from(message: String) -> Self {
+        QuantizeError::Failed(message)
+    }
+}
+
+impl fmt::Display for QuantizeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            QuantizeError::QualityTooLow => f.write_str("quality too low"),
+            QuantizeError::Failed(message) => f.write_str(message),
+        }
+    }
+}
+
+/// 設定から imagequant の属性を作る
+fn attributes(quality: u8, options: &QuantizeOptions) -> Result<imagequant::Attributes, String> {
+    let mut attrs = imagequant::new();
+    attrs
+        .set_quality(options.min_quality(quality), quality)
+        .map_err(|e| format!("imagequant quality: {:?}", e))?;
+    attrs
+        .set_speed(options.speed.clamp(1, 10))
+        .map_err(|e| format!("imagequant speed: {:?}", e))?;
+    attrs
+        .set_max_colors(options.max_colors.clamp(2, 256))
+        .map_err(|e| format!("imagequant max colors: {:?}", e))?;
+    attrs
+        .set_min_posterization(options.posterization.min(4))
+        .map_err(|e| format!("imagequant posterization: {:?}", e))?;
+    Ok(attrs)
+}
+
+/// RGBA 画像を imagequant 用のピクセル列に変換
+fn to_pixels(image: &RgbaImage) -> Vec<RGBA> {
+    image
+        .pixels()
+        .map(|p| RGBA::new(p[0], p[1], p[2], p[3]))
+        .collect()
+}
+
+/// 1 枚の画像を減色する
+pub fn quantize(
+    image: &RgbaImage,
+    quality: u8,
+    options: &QuantizeOptions,
+) -> Result<Quantized, QuantizeError> {
+    if let Some(ref palette) = options.palette {
+        // 共通パレットが最低クオリティを満たせなかった場合は 1 枚ずつ減色したときと同じく代替処理に任せる
+        if options.palette_below_min_quality {
+            return Err(QuantizeError::QualityTooLow);
+        }
+        return Ok(remap(image, palette, options)?);
+    }
+
+    let attrs = attributes(quality, options)?;
+    let (width, height) = image.dimensions();
+
+    let mut liq_image = attrs
+        .new_image(to_pixels(image), width as usize, height as usize, 0.0)
+        .map_err(|e| format!("imagequant: {:?}", e))?;
+    let mut quantized = attrs.quantize(&mut liq_image).map_err(|e| match e {
+        imagequant::Error::QualityTooLow => QuantizeError::QualityTooLow,
+        e => QuantizeError::Failed(format!("imagequant: {:?}", e)),
+    })?;
+    let _ = quantized.set_dithering_level(options.dithering.clamp(0.0, 1.0));
+
+    quantized
+        .remapped(&mut liq_image)
+        .map_err(|e| QuantizeError::Failed(format!("imagequant remap: {:?}", e)))
+}
+
+/// 複数の画像 (アニメーションのフレームなど) を共通パレットで減色する
+/// パレットは全画像のヒストグラムから作り、各画像をそのパレットにリマップする
+pub fn quantize_shared(
+    images: &[&RgbaImage],
+    quality: u8,
+    options: &QuantizeOptions,
+) -> Result<(Vec<RGBA>, Vec<Vec<u8>>), String> {
+    let palette = match options.palette {
+        Some(ref palette) => palette.iter().take(256).copied().collect(),
+        None => {
+            let mut builder = PaletteBuilder::new(quality, options)?;
+            for image in images {
+                builder.add(image)?;
+            }
+            builder.finish().map_err(|e| e.to_string())?
+        }
+    };
+
+    let indexed = images
+        .iter()
+        .map(|image| remap(image, &palette, options).map(|(_, indexed)| indexed))
+        .collect::<Result<_, _>>()?;
+    Ok((palette, indexed))
+}
+
+/// 指定したパレットにリマップする (imagequant の固定色として全色を登録する)
+/// 返すパレットとインデックスは指定したパレットの順序に揃える
+fn remap(
+    image: &RgbaImage,
+    palette: &[RGBA],
+    options: &QuantizeOptions,
+) -> Result<Quantized, String> {
+    let palette = &palette[..palette.len().min(256)];
+    if palette.is_empty() {
+        return Err("palette is empty".to_string());
+    }
+
+    let mut attrs = imagequant::new();
+    attrs
+        .set_max_colors(palette.len().max(2) as u32)
+        .map_err(|e| format!("imagequant max colors: {:?}", e))?;
+    attrs
+        .set_speed(options.speed.clamp(1, 10))
+        .map_err(|e| format!("imagequant speed: {:?}", e))?;
+
+    let (width, height) = image.dimensions();
+    let mut liq_image = attrs
+        .new_image(to_pixels(image), width as usize, height as usize, 0.0)
+        .map_err(|e| format!("imagequant: {:?}", e))?;
+    for &color in palette {
+        liq_image
+            .add_fixed_color(color)
+            .map_err(|e| format!("imagequant fixed color: {:?}", e))?;
+    }
+
+    let mut quantized = attrs
+        .quantize(&mut liq_image)
+        .map_err(|e| format!("imagequant: {:?}", e))?;
+    let _ = quantized.set_dithering_level(options.dithering.clamp(0.0, 1.0));
+    let (image_palette, indexed_pixels) = quantized
+        .remapped(&mut liq_image)
+        .map_err(|e| format!("imagequant remap: {:?}", e))?;
+
+    // imagequant は固定色の順序を入れ替えるため、指定したパレットの番号に変換する
+    let order: Vec<u8> = image_palette
+        .iter()
+        .map(|&color| nearest(palette, color))
+        .collect();
+    let indexed_pixels = indexed_pixels
+        .into_iter()
+        .map(|i| order.get(i as usize).copied().unwrap_or(0))
+        .collect();
+
+    Ok((palette.to_vec(), indexed_pixels))
+}
+
+/// パレット中で最も近い色の番号
+fn nearest(palette: &[RGBA], color: RGBA) -> u8 {
+    let distance = |c: &RGBA| {
+        let d = |a: u8, b: u8| (a as i32 - b as i32).pow(2);
+        d(c.r, color.r) + d(c.g, color.g) + d(c.b, color.b) + d(c.a, color.a)
+    };
+    (0..palette.len())
+        .min_by_key(|&i| distance(&palette[i]))
+        .unwrap_or(0) as u8
+}
+
+/// 複数の画像のヒストグラムから共通パレットを作る
+/// 画像は 1 枚ずつ追加できるため、一括処理でも全画像をメモリに保持しない
+pub struct PaletteBuilder {
+    attrs: imagequant::Attributes,
+    histogram: imagequant::Histogram,
+    quality: u8,
+}
+
+impl PaletteBuilder {
+    pub fn new(quality: u8, options: &QuantizeOptions) -> Result<Self, String> {
+        let attrs = attributes(quality, options)?;
+        let histogram = imagequant::Histogram::new(&attrs);
+        Ok(PaletteBuilder {
+            attrs,
+            histogram,
+            quality,
+        })
+    }
+
+    /// 画像の色をヒストグラムに追加する
+    pub fn add(&mut self, image: &RgbaImage) -> Result<(), String> {
+        let (width, height) = image.dimensions();
+        let mut liq_image = self
+            .attrs
+            .new_image(to_pixels(image), width as usize, height as usize, 0.0)
+            .map_err(|e| format!("imagequant: {:?}", e))?;
+        self.histogram
+            .add_image(&self.attrs, &mut liq_image)
+            .map_err(|e| format!("imagequant histogram: {:?}", e))
+    }
+
+    /// 最低クオリティを 0 にする (finish が QualityTooLow を返した後の再試行用)
+    pub fn relax(&mut self) -> Result<(), String> {
+        self.attrs
+            .set_quality(0, self.quality)
+            .map_err(|e| format!("imagequant quality: {:?}", e))
+    }
+
+    /// 追加した全画像に共通のパレットを生成する
+    pub fn finish(&mut self) -> Result<Vec<RGBA>, QuantizeError> {
+        let mut quantized = self.histogram.quantize(&self.attrs).map_err(|e| match e {
+            imagequant::Error::QualityTooLow => QuantizeError::QualityTooLow,
+            e => QuantizeError::Failed(format!("imagequant: {:?}", e)),
+        })?;
+        Ok(quantized.palette_vec())
+    }
+}
+
+/// アルファを 0 / 255 の 2 値にする (GIF の 1 ビット透過用)
+pub fn threshold_alpha(image: &RgbaImage) -> RgbaImage {
+    let mut image = image.clone();
+    for p in image.pixels_mut() {
+        if p[3] < 128 {
+            p.0 = [0, 0, 0, 0];
+        } else {
+            p[3] = 255;
+        }
+    }
+    image
+}
@@ -0,0 +1,265 @@
+use std::collections::HashMap;
+use std::sync::{Arc, OnceLock, RwLock};
+
+use image::DynamicImage;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::i18n::Locale;
+
+// ============================================================================
+// 変換ステージ (透かし・色調補正などの独自処理の拡張点)
+// ============================================================================
+
+/// 変換ステージ
+/// リサイズ後・エンコード前の画像を受け取り、変換後の画像と処理ステップの説明を返す
+pub trait Stage: Send + Sync {
+    fn apply(&self, img: DynamicImage, params: &Value) -> Result<(DynamicImage, String), String>;
+
+    /// 指定した言語の説明 (None の場合は apply が返した説明を使う)
+    fn describe(&self, _params: &Value, _locale: Locale) -> Option<String> {
+        None
+    }
+}
+
+/// 関数をそのままステージとして登録できるようにする
+impl<F> Stage for F
+where
+    F: Fn(DynamicImage, &Value) -> Result<(DynamicImage, String), String> + Send + Sync,
+{
+    fn apply(&self, img: DynamicImage, params: &Value) -> Result<(DynamicImage, String), String> {
+        self(img, params)
+    }
+}
+
+/// パイプラインに追加するステージ 1 件 (指定した順に適用する)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StageSpec {
+    // 登録済みステージの名前
+    pub name: String,
+    // ステージに渡すパラメータ
+    #[serde(default)]
+    pub params: Value,
+}
+
+type Registry = RwLock<HashMap<String, Arc<dyn Stage>>>;
+
+/// 登録済みステージ (組み込みステージは初回アクセス時に登録する)
+static REGISTRY: OnceLock<Registry> = OnceLock::new();
+
+fn registry() -> &'static Registry {
+    REGISTRY.get_or_init(|| {
+        let mut stages: HashMap<String, Arc<dyn Stage>> = HashMap::new();
+        let builtins: [(&str, Builtin); 7] = [
+            ("grayscale", Builtin(grayscale, describe_grayscale)),
+            (
+                "flip_horizontal",
+                Builtin(flip_horizontal, describe_flip_horizontal),
+            ),
+            (
+                "flip_vertical",
+                Builtin(flip_vertical, describe_flip_vertical),
+            ),
+            ("rotate", Builtin(rotate, describe_rotate)),
+            ("brightness", Builtin(brightness, describe_brightness)),
+            ("contrast", Builtin(contrast, describe_contrast)),
+            ("blur", Builtin(blur, describe_blur)),
+        ];
+        for (name, builtin) in builtins {
+            stages.insert(name.to_string(), Arc::new(builtin));
+        }
+        RwLock::new(stages)
+    })
+}
+
+/// ステージを登録する (同名のステージは置き換える)
+pub fn register_stage(name: &str, stage: Arc<dyn Stage>) {
+    registry()
+        .write()
+        .unwrap_or_else(|e| e.into_inner())
+        .insert(name.to_string(), stage);
+}
+
+/// 名前からステージを取得する
+fn lookup(name: &str) -> Result<Arc<dyn Stage>, String> {
+    registry()
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .get(name)
+        .cloned()
+        .ok_or_else(|| format!("unknown stage: {}", name))
+}
+
+/// 登録済みステージの指定した言語での説明 (ステージが言語別の説明を持たない場合は None)
+pub fn describe(name: &str, params: &Value, locale: Locale) -> Option<String> {
+    lookup(name).ok()?.describe(params, locale)
+}
+
+/// 指定されたステージを順に適用し、各ステージの説明を返す
+pub fn apply_all(
+    mut img: DynamicImage,
+    specs: &[StageSpec],
+) -> Result<(DynamicImage, Vec<String>), String> {
+    let mut descriptions = Vec::with_capacity(specs.len());
+    for spec in specs {
+        let (next, description) = lookup(&spec.name)?
+            .apply(img, &spec.params)
+            .map_err(|e| format!("{}: {}", spec.name, e))?;
+        img = next;
+        descriptions.push(description);
+    }
+    Ok((img, descriptions))
+}
+
+// ============================================================================
+// 組み込みステージ
+// ============================================================================
+
+/// 組み込みステージ (変換と、言語別の説明)
+struct Builtin(
+    fn(DynamicImage, &Value) -> Result<DynamicImage, String>,
+    fn(&Value, Locale) -> String,
+);
+
+impl Stage for Builtin {
+    fn apply(&self, img: DynamicImage, params: &Value) -> Result<(DynamicImage, String), String> {
+        Ok(((self.0)(img, params)?, (self.1)(params, Locale::default())))
+    }
+
+    fn describe(&self, params: &Value, locale: Locale) -> Option<String> {
+        Some((self.1)(params, locale))
+    }
+}
+
+/// 数値パラメータを取り出す ({"value": 1.0} または値そのもの)
+fn number(params: &Value, key: &str) -> Result<f64, String> {
+    params
+        .get(key)
+        .unwrap_or(params)
+        .as_f64()
+        .ok_or_else(|| format!("parameter {} must be a number", key))
+}
+
+/// グレースケール化 (アルファは保持)
+fn grayscale(img: DynamicImage, _: &Value) -> Result<DynamicImage, String> {
+    Ok(if img.color().has_alpha() {
+        DynamicImage::ImageLumaA8(img.to_luma_alpha8())
+    } else {
+        DynamicImage::ImageLuma8(img.to_luma8())
+    })
+}
+
+fn describe_grayscale(_: &Value, locale: Locale) -> String {
+    locale.pick("グレースケール", "Grayscale").to_string()
+}
+
+/// 左右反転
+fn flip_horizontal(img: DynamicImage, _: &Value) -> Result<DynamicImage, String> {
+    Ok(img.fliph())
+}
+
+fn describe_flip_horizontal(_: &Value, locale: Locale) -> String {
+    locale.pick("左右反転", "Flip horizontal").to_string()
+}
+
+/// 上下反転
+fn flip_vertical(img: DynamicImage, _: &Value) -> Result<DynamicImage, String> {
+    Ok(img.flipv())
+}
+
+fn describe_flip_vertical(_: &Value, locale: Locale) -> String {
+    locale.pick("上下反転", "Flip vertical").to_string()
+}
+
+/// 時計回りに回転 ({"degrees": 90 | 180 | 270})
+fn rotate(img: DynamicImage, params: &Value) -> Result<DynamicImage, String> {
+    let degrees = number(params, "degrees")? as i64;
+    match degrees.rem_euclid(360) {
+        0 => Ok(img),
+        90 => Ok(img.rotate90()),
+        180 => Ok(img.rotate180()),
+        270 => Ok(img.rotate270()),
+        _ => Err("degrees must be a multiple of 90".to_string()),
+    }
+}
+
+fn describe_rotate(params: &Value, locale: Locale) -> String {
+    let degrees = number(params, "degrees").unwrap_or_default() as i64;
+    match locale {
+        Locale::Ja => format!("回転: {} 度", degrees),
+        Locale::En => format!("Rotate: {}°", degrees),
+    }
+}
+
+/// 明るさ調整 ({"value": -255 〜 255})
+fn brightness(img: DynamicImage, params: &Value) -> Result<DynamicImage, String> {
+    Ok(img.brighten(brightness_value(params)?))
+}
+
+fn brightness_value(params: &Value) -> Result<i32, String> {
+    Ok(number(params, "value")?.clamp(-255.0, 255.0) as i32)
+}
+
+fn describe_brightness(params: &Value, locale: Locale) -> String {
+    let value = brightness_value(params).unwrap_or_default();
+    format!("{}: {:+}", locale.pick("明るさ", "Brightness"), value)
+}
+
+/// コントラスト調整 ({"value": -100.0 〜 100.0})
+fn contrast(img: DynamicImage, params: &Value) -> Result<DynamicImage, String> {
+    Ok(img.adjust_contrast(contrast_value(params)?))
+}
+
+fn contrast_value(params: &Value) -> Result<f32, String> {
+    Ok(number(params, "value")?.clamp(-100.0, 100.0) as f32)
+}
+
+fn describe_contrast(params: &Value, locale: Locale) -> String {
+    let value = contrast_value(params).unwrap_or_default();
+    format!("{}: {:+}", locale.pick("コントラスト", "Contrast"), value)
+}
+
+/// ガウスぼかし ({"sigma": 0.1 〜})
+fn blur(img: DynamicImage, params: &Value) -> Result<DynamicImage, String> {
+    Ok(img.blur(blur_sigma(params)?))
+}
+
+fn blur_sigma(params: &Value) -> Result<f32, String> {
+    Ok(number(params, "sigma")?.max(0.1) as f32)
+}
+
+fn describe_blur(params: &Value, locale: Locale) -> String {
+    let sigma = blur_sigma(params).unwrap_or(0.1);
+    format!("{}: σ {:.1}", locale.pick("ぼかし", "Blur"), sigma)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn builtin_stages_are_described_in_each_locale() {
+        let params = json!({ "degrees": 90 });
+        let img = DynamicImage::new_rgba8(4, 2);
+        let (rotated, description) = lookup("rotate").unwrap().apply(img, &params).unwrap();
+        assert_eq!(rotated.width(), 2);
+        assert_eq!(description, "回転: 90 度");
+        assert_eq!(
+            describe("rotate", &params, Locale::En).as_deref(),
+            Some("Rotate: 90°")
+        );
+        assert_eq!(
+            describe("brightness", &json!(300), Locale::En).as_deref(),
+            Some("Brightness: +255")
+        );
+
+        // 言語別の説明を持たない独自ステージは None
+        register_stage(
+            "custom",
+            Arc::new(|img: DynamicImage, _: &Value| Ok((img, "custom".to_string()))),
+        );
+        assert_eq!(describe("custom", &Value::Null, Locale::En), None);
+    }
+}
@@ -0,0 +1,134 @@
+use std::fs;
+use std::io::Read;
+use std::path::Path;
+use std::sync::{Arc, OnceLock};
+
+use image::{DynamicImage, RgbaImage};
+use resvg::{tiny_skia, usvg};
+
+use crate::detect_format;
+
+// ============================================================================
+// SVG 入力 (resvg によるラスタライズ)
+// ============================================================================
+
+/// システムフォント (初回のみ読み込み、全スレッドで共有)
+static FONT_DB: OnceLock<Arc<usvg::fontdb::Database>> = OnceLock::new();
+
+fn font_db() -> Arc<usvg::fontdb::Database> {
+    FONT_DB
+        .get_or_init(|| {
+            let mut db = usvg::fontdb::Database::new();
+            db.load_system_fonts();
+            Arc::new(db)
+        })
+        .clone()
+}
+
+/// SVG ファイルかどうか (ラスター形式のシグネチャがなく、内容が XML で <svg を含むか .svgz の gzip)
+pub fn is_svg(path: &Path) -> bool {
+    if detect_format(path).is_some() {
+        return false;
+    }
+
+    let mut header = Vec::with_capacity(1024);
+    if fs::File::open(path)
+        .and_then(|f| f.take(1024).read_to_end(&mut header))
+        .is_err()
+    {
+        return false;
+    }
+    if header.starts_with(&[0x1f, 0x8b]) {
+        return path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("svgz"));
+    }
+    let text = String::from_utf8_lossy(&header);
+    let text = text.trim_start_matches('\u{feff}').trim_start();
+    text.starts_with('<') && text.contains("<svg")
+}
+
+/// SVG を読み込んでパースする (svgz も可、相対パスの画像はファイルの場所から解決)
+pub fn load(path: &Path) -> Result<usvg::Tree, String> {
+    let data = fs::read(path).map_err(|e| e.to_string())?;
+    let options = usvg::Options {
+        resources_dir: path.parent().map(Path::to_path_buf),
+        fontdb: font_db(),
+        ..Default::default()
+    };
+    usvg::Tree::from_data(&data, &options).map_err(|e| e.to_string())
+}
+
+/// SVG 本来の寸法 (width / height 属性、なければ viewBox)
+pub fn intrinsic_size(tree: &usvg::Tree) -> (u32, u32) {
+    let size = tree.size();
+    (
+        (size.width().ceil() as u32).max(1),
+        (size.height().ceil() as u32).max(1),
+    )
+}
+
+/// 指定サイズで直接ラスタライズする (ラスタライズ後の拡大縮小は行わない)
+pub fn render(tree: &usvg::Tree, width: u32, height: u32) -> Result<DynamicImage, String> {
+    let mut pixmap = tiny_skia::Pixmap::new(width, height)
+        .ok_or_else(|| format!("invalid SVG render size: {}x{}", width, height))?;
+
+    let size = tree.size();
+    let transform = tiny_skia::Transform::from_scale(
+        width as f32 / size.width(),
+        height as f32 / size.height(),
+    );
+    resvg::render(tree, transform, &mut pixmap.as_mut());
+
+    // tiny-skia は乗算済みアルファのため通常の RGBA に戻す
+    let rgba: Vec<u8> = pixmap
+        .pixels()
+        .iter()
+        .flat_map(|p| {
+            let c = p.demultiply();
+            [c.red(), c.green(), c.blue(), c.alpha()]
+        })
+        .collect();
+
+    RgbaImage::from_raw(width, height, rgba)
+        .map(DynamicImage::ImageRgba8)
+        .ok_or_else(|| "SVG rasterization failed".to_string())
+}
+
+/// 本来の寸法でラスタライズする
+pub fn render_intrinsic(tree: &usvg::Tree) -> Result<DynamicImage, String> {
+    let (width, height) = intrinsic_size(tree);
+    render(tree, width, height)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn content_decides_over_extension() {
+        let dir = std::env::temp_dir().join(format!("svg_sniff_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+
+        let png = dir.join("raster.svg");
+        RgbaImage::new(2, 2)
+            .save_with_format(&png, image::ImageFormat::Png)
+            .unwrap();
+        assert!(!is_svg(&png));
+
+        let svg = dir.join("vector.bin");
+        fs::write(
+            &svg,
+            "<!-- logo -->\n<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"4\" height=\"4\"/>",
+        )
+        .unwrap();
+        assert!(is_svg(&svg));
+
+        let text = dir.join("notes.svg");
+        fs::write(&text, "not an image").unwrap();
+        assert!(!is_svg(&text));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
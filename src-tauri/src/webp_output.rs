@@ -0,0 +1,145 @@
+use image::DynamicImage;
+use serde::{Deserialize, Serialize};
+
+use crate::depth;
+use crate::i18n::Locale;
+
+// ============================================================================
+// WebP 出力 (libwebp の詳細設定)
+// ============================================================================
+
+/// ロスレス圧縮レベルごとの (method, quality) (libwebp の WebPConfigLosslessPreset と同じ)
+const LOSSLESS_PRESETS: [(i32, f32); 10] = [
+    (0, 0.0),
+    (1, 20.0),
+    (2, 25.0),
+    (3, 30.0),
+    (3, 50.0),
+    (4, 50.0),
+    (4, 75.0),
+    (4, 90.0),
+    (5, 90.0),
+    (6, 100.0),
+];
+
+/// WebP エンコードオプション
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebpOptions {
+    // ロスレス圧縮 (クオリティは使わず lossless_level で圧縮率を決める)
+    #[serde(default)]
+    pub lossless: bool,
+    // ロスレス圧縮レベル (0 〜 9、lossless 時のみ)
+    #[serde(default = "default_lossless_level")]
+    pub lossless_level: u8,
+    // 圧縮メソッド (0 〜 6、大きいほど遅く高圧縮、ロッシー時のみ)
+    #[serde(default = "default_method")]
+    pub method: u8,
+    // ニアロスレスの前処理 (0 〜 100、100 で無効、lossless 時のみ)
+    #[serde(default = "default_near_lossless")]
+    pub near_lossless: u8,
+    // アルファチャンネルのクオリティ (0 〜 100)
+    #[serde(default = "default_alpha_quality")]
+    pub alpha_quality: u8,
+    // 完全に透明なピクセルの RGB 値も保持する
+    #[serde(default)]
+    pub exact: bool,
+    // シャープ YUV 変換 (ロッシー時の色にじみを抑える、低速)
+    #[serde(default)]
+    pub sharp_yuv: bool,
+}
+
+fn default_lossless_level() -> u8 {
+    6
+}
+
+fn default_method() -> u8 {
+    4
+}
+
+fn default_near_lossless() -> u8 {
+    100
+}
+
+fn default_alpha_quality() -> u8 {
+    100
+}
+
+impl Default for WebpOptions {
+    fn default() -> Self {
+        WebpOptions {
+            lossless: false,
+            lossless_level: default_lossless_level(),
+            method: default_method(),
+            near_lossless: default_near_lossless(),
+            alpha_quality: default_alpha_quality(),
+            exact: false,
+            sharp_yuv: false,
+        }
+    }
+}
+
+impl WebpOptions {
+    /// libwebp の設定を組み立てる
+    pub fn config(&self, quality: u8) -> Result<webp::WebPConfig, String> {
+        let mut config =
+            webp::WebPConfig::new().map_err(|_| "WebPConfigInit failed".to_string())?;
+
+        if self.lossless {
+            let (method, quality) = LOSSLESS_PRESETS[self.lossless_level.min(9) as usize];
+            config.lossless = 1;
+            config.method = method;
+            config.quality = quality;
+            config.near_lossless = self.near_lossless.min(100) as i32;
+        } else {
+            config.quality = quality.min(100) as f32;
+            config.method = self.method.min(6) as i32;
+            config.use_sharp_yuv = self.sharp_yuv as i32;
+        }
+        config.alpha_quality = self.alpha_quality.min(100) as i32;
+        config.exact = self.exact as i32;
+
+        Ok(config)
+    }
+
+    /// 処理ステップ表示用の説明
+    pub fn describe(&self, quality: u8, locale: Locale) -> String {
+        let level = locale.pick("レベル", "level");
+        if !self.lossless {
+            return format!(
+                "{} {}, {} {}",
+                locale.pick("クオリティ", "quality"),
+                quality,
+                locale.pick("メソッド", "method"),
+                self.method.min(6)
+            );
+        }
+        if self.near_lossless < 100 {
+            format!(
+                "{} {}, {} {}",
+                locale.pick("ニアロスレス", "near-lossless"),
+                self.near_lossless,
+                level,
+                self.lossless_level.min(9)
+            )
+        } else {
+            format!(
+                "{}, {} {}",
+                locale.pick("ロスレス", "lossless"),
+                level,
+                self.lossless_level.min(9)
+            )
+        }
+    }
+}
+
+/// デコード済み画像を WebP としてエンコードする
+pub fn encode(img: &DynamicImage, quality: u8, options: &WebpOptions) -> Result<Vec<u8>, String> {
+    let rgba_img = depth::to_rgba8(img);
+    let (width, height) = rgba_img.dimensions();
+    let config = options.config(quality)?;
+
+    webp::Encoder::from_rgba(rgba_img.as_raw(), width, height)
+        .encode_advanced(&config)
+        .map(|data| data.to_vec())
+        .map_err(|e| format!("{:?}", e))
+}
@@ -4,6 +4,44 @@ export interface ImageInfo {
   height: number;
   size: number;
   original_path: string;
+  // ファイル内容から判別したフォーマット ("png", "jpeg", "svg" など)
+  format: string;
+  // カラータイプ ("Rgba8" など)
+  color_type: string;
+  // 1 チャンネルあたりのビット深度
+  bit_depth: number;
+  // フレーム数 (静止画は 1)
+  frame_count: number;
+  // 色解析結果 (analyzeColors 指定時のみ)
+  colors: ColorAnalysis | null;
+}
+
+// 画像情報の取得結果
+export interface ImageInfoResult {
+  // 読み取れた画像 (色解析のみ失敗した場合は colors なしで含める)
+  images: ImageInfo[];
+  // 読み取れなかった・色解析に失敗したファイルのエラー
+  errors: ProcessError[];
+}
+
+export interface PaletteColor {
+  // "#rrggbb"
+  color: string;
+  // 画像中に占める割合 (0.0 〜 1.0)
+  ratio: number;
+}
+
+export interface ColorAnalysis {
+  // 平均色 "#rrggbb" (アルファで重み付け)
+  average_color: string;
+  // 支配色 "#rrggbb" (パレットの先頭)
+  dominant_color: string;
+  // 代表パレット (割合の大きい順)
+  palette: PaletteColor[];
+  // 半透明・透明ピクセルが実際に存在するか
+  has_alpha: boolean;
+  // 全ピクセルが無彩色か
+  is_grayscale: boolean;
 }
 
 export interface ResizeOptions {
@@ -12,11 +50,132 @@ export interface ResizeOptions {
   maintain_aspect_ratio: boolean;
 }
 
-export interface QuantOptions {
+export interface QuantizeOptions {
+  // ディザリングの強さ (0.0 〜 1.0、既定 1.0)
+  dithering?: number;
+  // 速度 (1 〜 10、既定 4、小さいほど遅く高品質)
+  speed?: number;
+  // パレットの最大色数 (2 〜 256、既定 256)
+  max_colors?: number;
+  // 最低クオリティ (null の場合は最高クオリティ - 10)
+  min_quality?: number | null;
+  // ポスタリゼーションのビット数 (0 〜 4)
+  posterization?: number;
+  // 固定パレットの指定 (指定時はパレットを生成せず、この色にリマップする)
+  fixed_palette?: PaletteSource | null;
+}
+
+// 固定パレットの指定方法
+// colors: 16 進カラーコード (#rgb / #rrggbb / #rrggbbaa) の一覧
+// file: パレットファイル (.gpl / .act / 参照用 PNG / 1 行 1 色のカラーコード一覧)
+export type PaletteSource = { colors: string[] } | { file: string };
+
+// pngquant が最低クオリティを満たせなかったときの対応
+// fail: エラー / truecolor: フルカラー PNG + oxipng / lower_quality: 最低クオリティ 0 で再試行 / skip: 出力しない
+export type QualityFallback = "fail" | "truecolor" | "lower_quality" | "skip";
+
+// メッセージ・警告の表示言語
+export type Locale = "ja" | "en";
+
+// 変換ステージ 1 件 (リサイズ後に指定した順で適用)
+// 組み込み: grayscale / flip_horizontal / flip_vertical / rotate ({ degrees }) /
+//           brightness ({ value }) / contrast ({ value }) / blur ({ sigma })
+export interface StageSpec {
+  name: string;
+  params?: unknown;
+}
+
+// 一括処理の共通パレット設定
+export interface BatchPaletteOptions {
+  // パレットの書き出し先 (.gpl / .act / それ以外は 16 進カラーコードの一覧、null の場合は書き出さない)
+  export_path?: string | null;
+}
+
+export interface QuantOptions extends QuantizeOptions {
   quality: number;
 }
 
-export type OutputFormat = "png" | "webp";
+export interface JpegOptimizeOptions {
+  // プログレッシブ JPEG に変換する
+  progressive?: boolean;
+  // ICC プロファイル (APP2) を残す (既定 true)
+  keep_icc?: boolean;
+  // EXIF / XMP (APP1) を残す (回転情報もここに含まれるため既定 true)
+  keep_exif?: boolean;
+}
+
+export type OutputFormat = "png" | "webp" | "gif" | "jxl" | "jpeg";
+
+export interface OutputVariant {
+  width: number | null;
+  height: number | null;
+  format: OutputFormat;
+  quality: number;
+}
+
+export type PlaceholderHash = "blurhash" | "thumbhash";
+
+export interface PlaceholderOptions {
+  // BlurHash / ThumbHash (null の場合は生成しない)
+  hash?: PlaceholderHash | null;
+  // 極小 WebP の data URI を生成するか
+  preview?: boolean;
+}
+
+export interface AnimationOptions {
+  // 連続する同一フレームを 1 枚にまとめる (表示時間は合算)
+  drop_duplicates?: boolean;
+  // APNG 出力時に全フレーム共通の量子化パレットを使う
+  shared_palette?: boolean;
+}
+
+// keep: 元ファイルの設定を維持 / off: インターレースなし / adam7: Adam7
+export type Interlacing = "keep" | "off" | "adam7";
+
+export interface OxipngOptions {
+  // プリセット (0 〜 6、既定 4)
+  preset?: number;
+  // libdeflater の圧縮レベル (0 〜 12、既定 12、Zopfli 使用時は無視)
+  compression?: number;
+  // Zopfli の反復回数 (指定時は libdeflater の代わりに Zopfli を使う、非常に低速)
+  zopfli_iterations?: number | null;
+  // インターレース (既定 off)
+  interlace?: Interlacing;
+  // 透明ピクセルの色を変更して圧縮率を上げる (既定 true)
+  optimize_alpha?: boolean;
+  // フィルター選択を高速評価で絞り込む (既定 true)
+  fast_evaluation?: boolean;
+  // 1 ファイルあたりの処理時間の上限 (ミリ秒)
+  timeout_ms?: number | null;
+}
+
+export interface WebpOptions {
+  // ロスレス圧縮 (クオリティは使わず lossless_level で圧縮率を決める)
+  lossless?: boolean;
+  // ロスレス圧縮レベル (0 〜 9、既定 6、lossless 時のみ)
+  lossless_level?: number;
+  // 圧縮メソッド (0 〜 6、既定 4、ロッシー時のみ)
+  method?: number;
+  // ニアロスレスの前処理 (0 〜 100、100 で無効、lossless 時のみ)
+  near_lossless?: number;
+  // アルファチャンネルのクオリティ (0 〜 100、既定 100)
+  alpha_quality?: number;
+  // 完全に透明なピクセルの RGB 値も保持する
+  exact?: boolean;
+  // シャープ YUV 変換 (ロッシー時の色にじみを抑える、低速)
+  sharp_yuv?: boolean;
+}
+
+export interface JxlOptions {
+  // Butteraugli 距離 (0.0 〜 15.0、null の場合はクオリティから換算)
+  distance?: number | null;
+  // エンコード努力値 (1 〜 10、既定 7)
+  effort?: number;
+  // ピクセル単位のロスレス圧縮 (クオリティ 100 でも有効)
+  lossless?: boolean;
+  // JPEG 入力を元の JPEG に復元可能な形でロスレス変換する (既定 true)
+  lossless_jpeg?: boolean;
+}
 
 export interface ProcessOptions {
   // リサイズ設定
@@ -27,12 +186,48 @@ export interface ProcessOptions {
   // pngquant 設定 (PNG のみ)
   quantize_enabled: boolean;
   quality: number;
-  // oxipng 最適化設定 (PNG のみ)
+  // 減色の詳細設定 (ディザリング・速度・色数など、GIF・APNG の共通パレットにも適用)
+  quantize?: QuantizeOptions;
+  // 最適化設定 (PNG は oxipng、JPEG はハフマン符号の最適化)
   optimize_enabled: boolean;
+  // oxipng の詳細設定 (プリセット・Zopfli・インターレースなど)
+  oxipng?: OxipngOptions;
   // 出力先ディレクトリ (null の場合は元ファイルと同じ場所)
   output_dir: string | null;
   // 出力フォーマット
   output_format: OutputFormat;
+  // 出力バリアント (指定時はリサイズ設定・出力フォーマットの代わりに使用)
+  variants?: OutputVariant[];
+  // レスポンシブ画像マニフェスト (JSON) の出力先 (null の場合は出力しない)
+  manifest_path?: string | null;
+  // プレースホルダー生成設定 (null の場合は生成しない)
+  placeholder?: PlaceholderOptions | null;
+  // アニメーション設定 (既定で有効、null の場合は先頭フレームのみを静止画として処理)
+  animation?: AnimationOptions | null;
+  // WebP 出力設定
+  webp?: WebpOptions;
+  // JPEG XL 出力設定
+  jxl?: JxlOptions;
+  // JPEG 最適化設定 (JPEG 入力は再エンコードせずに最適化する)
+  jpeg?: JpegOptimizeOptions;
+  // 16 ビット入力をロスレス PNG 出力 (pngquant 無効) のとき 16 ビットのまま保持する
+  keep_16bit?: boolean;
+  // pngquant が最低クオリティを満たせなかったときの対応 (既定 "fail")
+  quality_fallback?: QualityFallback;
+  // 一括処理の全画像で共通のパレットを使う (pngquant 有効時のみ)
+  batch_palette?: BatchPaletteOptions | null;
+  // リサイズ後に順に適用する変換ステージ
+  stages?: StageSpec[];
+  // メッセージ・警告の表示言語 (既定 "ja")
+  locale?: Locale;
+}
+
+export interface OutputEntry {
+  output_path: string;
+  width: number;
+  height: number;
+  format: OutputFormat;
+  size: number;
 }
 
 export interface ProcessResult {
@@ -41,6 +236,85 @@ export interface ProcessResult {
   result_size: number;
   output_path: string;
   message: string;
+  // 生成した出力ファイル一覧 (バリアント指定時は複数)
+  outputs: OutputEntry[];
+  // 遅延読み込み用プレースホルダー
+  placeholder: Placeholder | null;
+  // 処理は成功したが注意が必要な事項 (拡張子と内容の不一致など)
+  warnings: Warning[];
+  // 元画像の 1 チャンネルあたりのビット深度 (不明な場合は 0)
+  source_bit_depth: number;
+  // 最低クオリティ不足で代替処理を行った場合、その内容
+  quality_fallback: QualityFallback | null;
+  // 失敗時のエラー (code で種類を判別できる)
+  error: ProcessError | null;
+  // ステージごとの処理内容・所要時間・処理後のサイズ
+  steps: StepResult[];
+}
+
+// パイプラインのステージ
+export type PipelineStage = "decode" | "transform" | "encode" | "optimize" | "write";
+
+// ステージ内で行った処理 1 件 (name: "resize" / "quantize" / "oxipng" など、params は処理ごとに異なる)
+export interface Step {
+  name: string;
+  params?: unknown;
+}
+
+// ステージ 1 件分の処理結果
+export interface StepResult {
+  // 対象の出力 (outputs のインデックス、デコードは全出力で共通のため null)
+  output: number | null;
+  stage: PipelineStage;
+  // ステージ内で行った処理とそのパラメータ
+  operations: Step[];
+  // 所要時間 (ミリ秒)
+  duration_ms: number;
+  // ステージ後のバイト数 (エンコード前は null)
+  size: number | null;
+  // ステージ後の寸法
+  width: number;
+  height: number;
+}
+
+// エラーの種類 (code は互換性のため変更しない)
+export type ErrorKind =
+  | { code: "not_found" }
+  | { code: "decode_failed"; details: { reason: string } }
+  | { code: "unsupported_format"; details: { reason: string } }
+  | { code: "transform_failed"; details: { reason: string } }
+  | { code: "quantize_failed"; details: { reason: string } }
+  | { code: "quality_too_low"; details: { min_quality: number; quality: number } }
+  | { code: "skipped"; details: { min_quality: number; quality: number } }
+  | { code: "encode_failed"; details: { format: OutputFormat; reason: string } }
+  | { code: "optimize_failed"; details: { reason: string } }
+  | { code: "write_failed"; details: { output_path: string; reason: string } }
+  | { code: "invalid_options"; details: { reason: string } };
+
+// 警告の種類 (code は互換性のため変更しない)
+export type WarningKind =
+  | { code: "extension_mismatch"; details: { extension: string; format: string } }
+  | { code: "first_frame_only"; details: { frames: number } }
+  | { code: "padded_to_square"; details: { width: number; height: number } }
+  | { code: "upscaled"; details: { size: number; width: number; height: number } };
+
+// 警告 (message は結果を返す時点の言語で表示したもの)
+export type Warning = WarningKind & {
+  message: string;
+};
+
+// 処理エラー (コマンドの失敗時にも返される)
+export type ProcessError = ErrorKind & {
+  // 対象ファイル (特定のファイルに関係しない場合は null)
+  path: string | null;
+};
+
+export interface Placeholder {
+  blurhash: string | null;
+  // ThumbHash (base64)
+  thumbhash: string | null;
+  // data:image/webp;base64,...
+  data_uri: string | null;
 }
 
 export interface ProgressPayload {
@@ -49,3 +323,40 @@ export interface ProgressPayload {
   current_file: string | null;
   result: ProcessResult | null;
 }
+
+// 完了イベントのペイロード
+export interface CompletePayload {
+  // 画像ごとの結果に含まれない一括処理全体のエラー (マニフェストの書き出しなど)
+  errors: ProcessError[];
+}
+
+export interface IconOptions {
+  // 出力先ディレクトリ (null の場合は元ファイルと同じ場所)
+  output_dir?: string | null;
+  // pngquant のクオリティ (既定 90)
+  quality?: number;
+  // pngquant による減色 (既定 true)
+  quantize_enabled?: boolean;
+  // oxipng による最適化 (既定 true)
+  optimize_enabled?: boolean;
+  // メッセージ・警告の表示言語 (既定 "ja")
+  locale?: Locale;
+}
+
+export interface IconFile {
+  output_path: string;
+  // 含まれる解像度 (ICO は複数)
+  sizes: number[];
+  size: number;
+}
+
+export interface IconSetResult {
+  original_size: number;
+  result_size: number;
+  files: IconFile[];
+  manifest_path: string;
+  // <head> に貼り付けるリンクタグ
+  html: string;
+  message: string;
+  warnings: Warning[];
+}